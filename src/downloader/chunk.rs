@@ -0,0 +1,135 @@
+//! Validation of downloaded DASH chunks, to catch error pages and incomplete responses before
+//! they end up in the output file.
+
+use thiserror::Error;
+
+use super::mp4::{parse_boxes, BoxError};
+
+const ACCEPTED_CONTENT_TYPES: [&str; 5] = [
+    "video/",
+    "audio/",
+    "application/mp4",
+    "application/octet-stream",
+    "binary/octet-stream",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChunkKind {
+    /// Initialization segment, the first chunk of a stream.
+    Init,
+    Media,
+}
+
+impl ChunkKind {
+    fn required_boxes(&self) -> [&'static str; 2] {
+        match self {
+            ChunkKind::Init => ["ftyp", "moov"],
+            ChunkKind::Media => ["moof", "mdat"],
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ChunkError {
+    #[error("unexpected content type {0:?}")]
+    ContentType(String),
+    #[error("received {received} of {expected} bytes")]
+    Length { expected: u64, received: u64 },
+    #[error(transparent)]
+    Box(#[from] BoxError),
+    #[error("missing {0} box")]
+    MissingBox(&'static str),
+}
+
+pub fn validate(
+    kind: ChunkKind,
+    content_type: Option<&str>,
+    content_length: Option<u64>,
+    data: &[u8],
+) -> Result<(), ChunkError> {
+    if let Some(content_type) = content_type {
+        let mime_type = content_type.split(';').next().unwrap().trim();
+        if !ACCEPTED_CONTENT_TYPES
+            .iter()
+            .any(|t| mime_type.starts_with(t))
+        {
+            return Err(ChunkError::ContentType(mime_type.to_owned()));
+        }
+    }
+    if let Some(expected) = content_length {
+        if expected != data.len() as u64 {
+            return Err(ChunkError::Length {
+                expected,
+                received: data.len() as u64,
+            });
+        }
+    }
+
+    let boxes = parse_boxes(data)?;
+    for required in kind.required_boxes() {
+        if !boxes.iter().any(|b| b.is(required)) {
+            return Err(ChunkError::MissingBox(required));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::{init_segment, media_segment};
+    use super::*;
+
+    #[test]
+    fn test_validate_valid_chunks() {
+        let init = init_segment();
+        let media = media_segment(b"data");
+        assert_eq!(
+            validate(
+                ChunkKind::Init,
+                Some("video/mp4"),
+                Some(init.len() as u64),
+                &init
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                ChunkKind::Media,
+                Some("application/octet-stream; charset=binary"),
+                None,
+                &media
+            ),
+            Ok(())
+        );
+        assert_eq!(validate(ChunkKind::Media, None, None, &media), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_invalid_chunks() {
+        let media = media_segment(b"data");
+        assert_eq!(
+            validate(
+                ChunkKind::Media,
+                Some("text/html; charset=utf-8"),
+                None,
+                b"<html></html>"
+            ),
+            Err(ChunkError::ContentType("text/html".to_owned()))
+        );
+        assert_eq!(
+            validate(ChunkKind::Media, None, Some(100), &media),
+            Err(ChunkError::Length {
+                expected: 100,
+                received: media.len() as u64
+            })
+        );
+        assert_eq!(
+            validate(ChunkKind::Media, None, None, &media[..media.len() - 1]),
+            Err(ChunkError::Box(BoxError::Truncated(8)))
+        );
+        assert_eq!(
+            validate(ChunkKind::Init, Some("video/mp4"), None, &media),
+            Err(ChunkError::MissingBox("ftyp"))
+        );
+    }
+}
@@ -2,12 +2,15 @@ use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 
-use tokio::sync::mpsc::{Sender, UnboundedReceiver};
+use chrono::{DateTime, Local};
+use tokio::sync::mpsc::{Sender, UnboundedReceiver, UnboundedSender};
 use tokio::sync::Notify;
 use tokio_util::sync::CancellationToken;
 
-use super::models::{OnErrorAction, QueueItem, StateUpdate};
-use super::{DownloadRequest, State};
+use super::history::{History, HistoryEntry};
+use super::models::{ListRequest, OnErrorAction, QueueItem, StateUpdate};
+use super::rate_limit::RateLimiter;
+use super::{DownloadRequest, Settings, ShowUrl, State, VideoUrl};
 
 pub struct Client {
     shutdown_token: CancellationToken,
@@ -16,10 +19,18 @@ pub struct Client {
     thread_handle: Option<JoinHandle<()>>,
     request_queue: Arc<Mutex<VecDeque<DownloadRequest>>>,
     worker_notifier: Arc<Notify>,
+    estimate_sender: UnboundedSender<DownloadRequest>,
+    list_sender: UnboundedSender<ListRequest>,
+    settings: Arc<Mutex<Settings>>,
+    settings_notifier: Arc<Notify>,
+    subscriptions_notifier: Arc<Notify>,
+    rate_limiter: Arc<RateLimiter>,
+    history: Arc<Mutex<History>>,
     state_update_receiver: UnboundedReceiver<StateUpdate>,
 }
 
 impl Client {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
         shutdown_token: CancellationToken,
         cancel_download_sender: Sender<()>,
@@ -27,6 +38,13 @@ impl Client {
         thread_handle: JoinHandle<()>,
         request_queue: Arc<Mutex<VecDeque<DownloadRequest>>>,
         worker_notifier: Arc<Notify>,
+        estimate_sender: UnboundedSender<DownloadRequest>,
+        list_sender: UnboundedSender<ListRequest>,
+        settings: Arc<Mutex<Settings>>,
+        settings_notifier: Arc<Notify>,
+        subscriptions_notifier: Arc<Notify>,
+        rate_limiter: Arc<RateLimiter>,
+        history: Arc<Mutex<History>>,
         state_update_receiver: UnboundedReceiver<StateUpdate>,
     ) -> Self {
         Self {
@@ -36,6 +54,13 @@ impl Client {
             thread_handle: Some(thread_handle),
             request_queue,
             worker_notifier,
+            estimate_sender,
+            list_sender,
+            settings,
+            settings_notifier,
+            subscriptions_notifier,
+            rate_limiter,
+            history,
             state_update_receiver,
         }
     }
@@ -44,8 +69,14 @@ impl Client {
         let qi = QueueItem {
             request_id: request.id(),
             title: request.url.as_str().to_owned(),
+            estimated_size: None,
+            not_before: None,
         };
 
+        if let Err(e) = self.estimate_sender.send(request.clone()) {
+            log::error!("could not send request for estimation: {}", e);
+        }
+
         let mut locked_queue = self.request_queue.lock().unwrap();
         state.enqueue(qi);
         locked_queue.push_back(request);
@@ -54,6 +85,20 @@ impl Client {
         self.worker_notifier.notify_one();
     }
 
+    /// Lists the episodes of a show, the result arrives as [`StateUpdate::ShowListed`].
+    pub fn list_show(&self, url: ShowUrl) {
+        if let Err(e) = self.list_sender.send(ListRequest::Show(url)) {
+            log::error!("could not send show for listing: {}", e);
+        }
+    }
+
+    /// Lists the segments of a video, the result arrives as [`StateUpdate::SegmentsListed`].
+    pub fn list_segments(&self, url: VideoUrl) {
+        if let Err(e) = self.list_sender.send(ListRequest::Segments(url)) {
+            log::error!("could not send video for listing its segments: {}", e);
+        }
+    }
+
     pub fn delete_download(&mut self, id: u32) {
         self.request_queue.lock().unwrap().retain(|r| r.id() != id);
     }
@@ -70,12 +115,68 @@ impl Client {
         }
     }
 
+    /// Keeps the failed request queued and starts it again at `not_before`.
+    pub fn defer(&self, not_before: DateTime<Local>) {
+        if let Err(e) = self
+            .on_error_sender
+            .blocking_send(OnErrorAction::Defer(not_before))
+        {
+            log::error!("could not send defer: {}", e);
+        }
+    }
+
     pub fn cancel_on_error(&self) {
         if let Err(e) = self.on_error_sender.blocking_send(OnErrorAction::Cancel) {
             log::error!("could not send cancel on error: {}", e);
         }
     }
 
+    pub fn update_settings(&self, settings: Settings) {
+        self.rate_limiter.configure(&settings);
+        let subscriptions_changed = {
+            let mut current = self.settings.lock().unwrap();
+            let changed = current.subscriptions_changed(&settings);
+            *current = settings;
+            changed
+        };
+        self.settings_notifier.notify_one();
+        if subscriptions_changed {
+            self.subscriptions_notifier.notify_one();
+        }
+    }
+
+    /// Latest download of the video, to warn before downloading it again.
+    pub fn find_in_history(&self, url: &VideoUrl) -> Option<HistoryEntry> {
+        self.history
+            .lock()
+            .unwrap()
+            .find(url.video_id(), url.segment_id().as_deref())
+            .cloned()
+    }
+
+    /// Latest download covering the request, including the segments picked for it.
+    pub fn find_request_in_history(&self, request: &DownloadRequest) -> Option<HistoryEntry> {
+        self.history
+            .lock()
+            .unwrap()
+            .find_selection(
+                request.url.video_id(),
+                request.url.segment_id().as_deref(),
+                request.segment_ids.as_deref(),
+            )
+            .cloned()
+    }
+
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        self.history.lock().unwrap().entries().to_vec()
+    }
+
+    pub fn remove_from_history(&self, entry: &HistoryEntry) {
+        if let Err(e) = self.history.lock().unwrap().remove(entry) {
+            log::error!("could not save the history: {}", e);
+        }
+    }
+
     pub fn poll_update(&mut self) -> Option<StateUpdate> {
         self.state_update_receiver.try_recv().ok()
     }
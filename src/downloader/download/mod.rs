@@ -1,33 +1,43 @@
-mod extract;
 mod mpd;
+mod verify;
 
 use std::ffi::OsStr;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::{bail, Context};
+use chrono::Local;
 use tempfile::TempDir;
-use tokio::process::Command;
 use tokio::{fs, try_join};
-use url::Url;
 
-use self::extract::VideoInfo::*;
-use self::extract::{extract_segment_url, extract_title, extract_video_info};
 use self::mpd::MediaUrls;
+use self::verify::verify;
+pub use self::verify::VerifyError;
+use super::extractor::{Analysis, Extractor, Video};
+use super::ffmpeg::{CheckCache, Ffmpeg};
+use super::history::HistoryEntry;
 use super::http::{HttpClient, Response};
-use super::{ClientRef, DownloadRequest, Error, Quality, StateUpdate};
+use super::progress::{ChunkEvent, ProgressTracker};
+use super::{
+    ClientRef, DownloadRequest, Error, Quality, SegmentOutput, Settings, StateUpdate, VerifyWarning,
+};
 
 async fn check_mp4_path(dir: &Path, file_stem: &str) -> Result<PathBuf, io::Error> {
+    check_path(dir, file_stem, ".mp4").await
+}
+
+/// Finds a path in `dir` that does not exist yet, appending a number to `stem` if necessary.
+async fn check_path(dir: &Path, stem: &str, extension: &str) -> Result<PathBuf, io::Error> {
     let mut file_suffix = None;
     let mut suffix_no = 1_u8;
 
     loop {
         let file_path = dir.join(format!(
-            "{}{}.mp4",
-            file_stem,
-            file_suffix.as_deref().unwrap_or_default()
+            "{}{}{}",
+            stem,
+            file_suffix.as_deref().unwrap_or_default(),
+            extension
         ));
         if fs::try_exists(&file_path).await? {
             if suffix_no == u8::MAX {
@@ -42,107 +52,399 @@ async fn check_mp4_path(dir: &Path, file_stem: &str) -> Result<PathBuf, io::Erro
     }
 }
 
-async fn run_ffmpeg<I, S>(args: I, opt_current_dir: Option<&Path>) -> anyhow::Result<()>
-where
-    I: IntoIterator<Item = S>,
-    S: AsRef<std::ffi::OsStr>,
-{
-    let mut c = Command::new("ffmpeg");
-    c.stdin(Stdio::null());
-    c.args(args);
-    if let Some(current_dir) = opt_current_dir {
-        c.current_dir(current_dir);
+/// Name of the file of a segment, numbered by its position in the episode.
+fn segment_file_name(idx: usize, total: usize, title: Option<&str>) -> String {
+    let width = total.to_string().len().max(2);
+    let title = match title {
+        Some(title) => sanitise_file_name::sanitise(title.trim()),
+        None => format!("Teil {}", idx + 1),
+    };
+    format!("{:0width$} - {}.mp4", idx + 1, title, width = width)
+}
+
+/// Escapes the characters with a special meaning in an ffmetadata file.
+fn escape_metadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
     }
+    escaped
+}
 
-    let output = c.output().await.context("failed to run ffmpeg")?;
-    log::debug!(
-        "stdout of ffmpeg: {}",
-        String::from_utf8_lossy(&output.stdout)
-    );
-    log::debug!(
-        "stderr of ffmpeg: {}",
-        String::from_utf8_lossy(&output.stderr)
-    );
-    if !output.status.success() {
-        bail!("ffmpeg exited with non-zero exit code");
+/// An ffmetadata file with a chapter for each segment, named after its title.
+fn chapters_metadata(chapters: &[(Option<&str>, Duration)]) -> String {
+    let mut metadata = String::from(";FFMETADATA1\n");
+    let mut start = 0;
+    for (idx, (title, duration)) in chapters.iter().enumerate() {
+        let end = start + duration.as_millis();
+        let title = match title {
+            Some(title) => escape_metadata(title.trim()),
+            None => format!("Teil {}", idx + 1),
+        };
+        metadata.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            start, end, title
+        ));
+        start = end;
     }
+    metadata
+}
 
+/// Moves the downloaded segments into a new folder in `dest_dir`, returning its path and the
+/// total size of the files.
+async fn save_segments(
+    segment_paths: &[PathBuf],
+    videos: &[Video],
+    dest_dir: &Path,
+    dir_name: &str,
+) -> Result<(PathBuf, u64), Error> {
+    let dir = check_path(dest_dir, dir_name, "").await?;
+    fs::create_dir(&dir).await?;
+    let mut size = 0;
+    for (idx, (path, video)) in segment_paths.iter().zip(videos).enumerate() {
+        let dest_path = dir.join(segment_file_name(
+            idx,
+            segment_paths.len(),
+            video.title.as_deref(),
+        ));
+        move_file(path, &dest_path).await?;
+        size += fs::metadata(&dest_path).await?.len();
+    }
+    Ok((dir, size))
+}
+
+/// Moves `from` to `to`, falling back to copying if they are on different file systems.
+async fn move_file(from: &Path, to: &Path) -> Result<(), io::Error> {
+    match fs::rename(from, to).await {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            log::debug!("could not rename {:?}, copying it instead", from);
+            if let Err(e) = fs::copy(from, to).await {
+                let _ = fs::remove_file(to).await;
+                return Err(e);
+            }
+            fs::remove_file(from).await
+        }
+        res => res,
+    }
+}
+
+fn merge_args<'a>(
+    video_path: &'a Path,
+    audio_path: &'a Path,
+    dest_path: &'a Path,
+) -> [&'a OsStr; 11] {
+    [
+        OsStr::new("-i"),
+        video_path.as_os_str(),
+        OsStr::new("-i"),
+        audio_path.as_os_str(),
+        OsStr::new("-codec"),
+        OsStr::new("copy"),
+        OsStr::new("-map"),
+        OsStr::new("0:v"),
+        OsStr::new("-map"),
+        OsStr::new("1:a"),
+        dest_path.as_os_str(),
+    ]
+}
+
+/// Creates a named pipe at `path` and opens it for writing. The pipe is opened read-write, so
+/// this doesn't block until ffmpeg opens it for reading.
+#[cfg(target_os = "linux")]
+fn create_pipe(path: &Path) -> Result<tokio::net::unix::pipe::Sender, Error> {
+    use nix::sys::stat::Mode;
+    use nix::unistd::mkfifo;
+    use tokio::net::unix::pipe;
+
+    mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR).map_err(io::Error::from)?;
+    Ok(pipe::OpenOptions::new()
+        .read_write(true)
+        .open_sender(path)?)
+}
+
+/// Returns the device id and the available space of the file system containing `path`.
+#[cfg(unix)]
+fn file_system_info(path: &Path) -> Result<Option<(u64, u64)>, io::Error> {
+    use std::os::unix::fs::MetadataExt;
+
+    use nix::sys::statvfs::statvfs;
+
+    let dev = std::fs::metadata(path)?.dev();
+    let stat = statvfs(path).map_err(io::Error::from)?;
+    Ok(Some((
+        dev,
+        stat.blocks_available() as u64 * stat.fragment_size() as u64,
+    )))
+}
+
+#[cfg(not(unix))]
+fn file_system_info(_path: &Path) -> Result<Option<(u64, u64)>, io::Error> {
+    Ok(None)
+}
+
+/// Fails if there isn't enough space left for a download of `size` bytes, of which `work_factor`
+/// times the size is needed temporarily in `work_dir` before the result is moved to `dest_dir`.
+fn check_free_space(
+    work_dir: &Path,
+    dest_dir: &Path,
+    size: u64,
+    work_factor: u64,
+) -> Result<(), Error> {
+    let (Some((work_dev, work_available)), Some((dest_dev, dest_available))) =
+        (file_system_info(work_dir)?, file_system_info(dest_dir)?)
+    else {
+        return Ok(());
+    };
+
+    let mut checks = vec![(work_dir, work_available, size * work_factor)];
+    if work_dev != dest_dev {
+        checks.push((dest_dir, dest_available, size));
+    }
+    for (dir, available, required) in checks {
+        log::debug!(
+            "{:?}: {} bytes required, {} available",
+            dir,
+            required,
+            available
+        );
+        if available < required {
+            return Err(Error::DiskSpaceError {
+                dir: dir.to_path_buf(),
+                required,
+                available,
+            });
+        }
+    }
     Ok(())
 }
 
+/// Verifies a downloaded file, collecting the problems instead of failing. The file is kept, as
+/// it may be complete despite a wrong duration in the manifest.
+async fn check_file(
+    path: &Path,
+    expected: Duration,
+    problems: &mut Vec<VerifyError>,
+) -> Result<(), Error> {
+    match verify(path, expected).await {
+        Err(Error::VerificationError(e)) => {
+            log::warn!("verification of {:?} failed: {}", path, e);
+            problems.push(e);
+            Ok(())
+        }
+        res => res,
+    }
+}
+
 async fn download_video(
     http_client: &HttpClient,
     client_ref: &ClientRef,
-    mpd_url: Url,
-    quality: Quality,
-    dest_dir: &Path,
+    ffmpeg: &Ffmpeg,
+    settings: &Settings,
+    media: MediaUrls,
     dest_path: &Path,
 ) -> Result<(), Error> {
-    let temp_dir: TempDir = TempDir::new_in(dest_dir)?;
-
-    let Response {
-        body: mpd_xml,
-        final_url,
-    } = http_client.get(mpd_url).await?;
-    let MediaUrls { video, audio } = mpd::get_urls(&final_url, &mpd_xml, quality)?;
-
-    let total_chunks = (video.len() + audio.len()) as f32;
-    let mut chunks_downloaded = 0_f32;
-    let mut last_progress = 0_f32;
-
-    let handle_chunk_downloaded = Arc::new(Mutex::new(|| {
-        chunks_downloaded += 1_f32;
-        let progress = chunks_downloaded / total_chunks;
-        if progress - last_progress > 0.01 || progress == 1_f32 {
-            client_ref.send(StateUpdate::Downloaded(progress));
-            last_progress = progress;
-            log::debug!("progress: {}", progress);
+    let temp_dir: TempDir = TempDir::new_in(dest_path.parent().unwrap())?;
+    let tracker = Arc::new(Mutex::new(ProgressTracker::new(&[
+        (media.video.len(), media.estimated_video_size()),
+        (media.audio.len(), media.estimated_audio_size()),
+    ])));
+    let on_chunk_event = |stream: usize| {
+        let tracker = tracker.clone();
+        let client_ref = client_ref.clone();
+        move |event: ChunkEvent| {
+            let maybe_progress = tracker
+                .lock()
+                .unwrap()
+                .update(stream, event, Instant::now());
+            if let Some(progress) = maybe_progress {
+                client_ref.send(StateUpdate::Downloaded(progress));
+            }
         }
-    }));
-    let handle_chunk_downloaded_clone = handle_chunk_downloaded.clone();
+    };
+    let MediaUrls { video, audio, .. } = media;
+
+    if settings.pipelined {
+        #[cfg(target_os = "linux")]
+        {
+            let video_path = temp_dir.path().join("video.pipe");
+            let audio_path = temp_dir.path().join("audio.pipe");
+            // file systems like SMB or FAT don't support named pipes
+            match create_pipe(&video_path).and_then(|v| Ok((v, create_pipe(&audio_path)?))) {
+                Ok((video_pipe, audio_pipe)) => {
+                    let dl_video = http_client.download_to(video_pipe, video, on_chunk_event(0));
+                    let dl_audio = http_client.download_to(audio_pipe, audio, on_chunk_event(1));
+                    let merge = async {
+                        ffmpeg
+                            .run(merge_args(&video_path, &audio_path, dest_path), None)
+                            .await
+                            .map_err(Error::from)
+                    };
+                    try_join!(dl_video, dl_audio, merge)?;
+                    return Ok(());
+                }
+                Err(e) => log::warn!(
+                    "could not create pipes in {:?}, downloading to files instead: {}",
+                    temp_dir.path(),
+                    e
+                ),
+            }
+        }
+    }
 
     let video_path = temp_dir.path().join("video.mp4");
-    let dl_video = http_client.download_to_file(&video_path, video, handle_chunk_downloaded);
+    let dl_video = http_client.download_to_file(&video_path, video, on_chunk_event(0));
     let audio_path = temp_dir.path().join("audio.mp4");
-    let dl_audio = http_client.download_to_file(&audio_path, audio, handle_chunk_downloaded_clone);
+    let dl_audio = http_client.download_to_file(&audio_path, audio, on_chunk_event(1));
     try_join!(dl_video, dl_audio)?;
 
     client_ref.send(StateUpdate::Merging);
-    run_ffmpeg(
-        &[
-            OsStr::new("-i"),
-            video_path.as_os_str(),
-            OsStr::new("-i"),
-            audio_path.as_os_str(),
-            OsStr::new("-codec"),
-            OsStr::new("copy"),
-            OsStr::new("-map"),
-            OsStr::new("0:v"),
-            OsStr::new("-map"),
-            OsStr::new("1:a"),
-            dest_path.as_os_str(),
-        ],
-        None,
-    )
-    .await?;
-
+    ffmpeg
+        .run(merge_args(&video_path, &audio_path, dest_path), None)
+        .await?;
     Ok(())
 }
 
+/// How to save the videos. Videos without segments are always saved as one file.
+fn segment_output(videos: &[Video], requested: SegmentOutput) -> SegmentOutput {
+    match videos {
+        [video] if video.segment_id.is_none() => SegmentOutput::Combined,
+        _ => requested,
+    }
+}
+
+/// Picks the videos of a request, the selected segments if it is restricted to some. With
+/// `by_segment` all segments are taken instead of a file of the whole episode.
+fn select_videos(
+    analysis: Analysis,
+    segment_ids: Option<&[String]>,
+    by_segment: bool,
+) -> Result<(String, Vec<Video>), Error> {
+    let Analysis {
+        title,
+        videos,
+        segments,
+    } = analysis;
+    let Some(segment_ids) = segment_ids else {
+        if by_segment && !segments.is_empty() {
+            return Ok((title, segments));
+        }
+        return Ok((title, videos));
+    };
+    let selected: Vec<Video> = segments
+        .into_iter()
+        .filter(|v| {
+            v.segment_id
+                .as_ref()
+                .is_some_and(|id| segment_ids.contains(id))
+        })
+        .collect();
+    if selected.len() != segment_ids.len() {
+        return Err(anyhow::anyhow!("selected segments not found").into());
+    }
+    Ok((title, selected))
+}
+
+async fn fetch_media_urls(
+    http_client: &HttpClient,
+    videos: &[Video],
+    quality: Quality,
+) -> Result<Vec<MediaUrls>, Error> {
+    let mut media = vec![];
+    for video in videos {
+        let Response {
+            body: mpd_xml,
+            final_url,
+        } = http_client.get(video.mpd_url.clone()).await?;
+        let m = mpd::get_urls(&final_url, &mpd_xml, quality).map_err(|source| Error::MpdError {
+            url: final_url,
+            source,
+        })?;
+        media.push(m);
+    }
+    Ok(media)
+}
+
+fn detect_geo_block(extractor: &dyn Extractor, e: Error) -> Error {
+    match e {
+        Error::HttpStatusError { url, status: 403 } if extractor.is_geo_restricted(&url) => {
+            Error::GeoBlockedError { url }
+        }
+        e => e,
+    }
+}
+
+/// Returns the title and the estimated size in bytes of a request.
+pub(super) async fn estimate(
+    http_client: &HttpClient,
+    request: &DownloadRequest,
+) -> Result<(String, u64), Error> {
+    estimate_request(http_client, request)
+        .await
+        .map_err(|e| detect_geo_block(request.url.extractor(), e))
+}
+
+async fn estimate_request(
+    http_client: &HttpClient,
+    request: &DownloadRequest,
+) -> Result<(String, u64), Error> {
+    let analysis = request
+        .url
+        .extractor()
+        .analyze(http_client, &request.url)
+        .await?;
+    let (title, videos) = select_videos(
+        analysis,
+        request.segment_ids.as_deref(),
+        request.segment_output != SegmentOutput::Combined,
+    )?;
+    let media = fetch_media_urls(http_client, &videos, request.quality).await?;
+    Ok((title, media.iter().map(MediaUrls::estimated_size).sum()))
+}
+
 pub(super) async fn download(
     http_client: &HttpClient,
     client_ref: &ClientRef,
+    settings: &Settings,
+    ffmpeg_check: &CheckCache,
     request: DownloadRequest,
-) -> Result<(), Error> {
+) -> Result<HistoryEntry, Error> {
+    let extractor = request.url.extractor();
+    download_request(http_client, client_ref, settings, ffmpeg_check, request)
+        .await
+        .map_err(|e| detect_geo_block(extractor, e))
+}
+
+async fn download_request(
+    http_client: &HttpClient,
+    client_ref: &ClientRef,
+    settings: &Settings,
+    ffmpeg_check: &CheckCache,
+    request: DownloadRequest,
+) -> Result<HistoryEntry, Error> {
     client_ref.send(StateUpdate::StartedRequest {
         request_id: request.id(),
+        url: request.url.as_ref().clone(),
     });
+    let ffmpeg = Ffmpeg::new(&settings.ffmpeg_path);
+    ffmpeg_check.ensure(&ffmpeg).await?;
 
     let id = request.url.video_id().to_owned();
-    let Response { body: html, .. } = http_client.get(request.url.as_ref().clone()).await?;
-    let title = extract_title(&html)?;
+    let analysis = request
+        .url
+        .extractor()
+        .analyze(http_client, &request.url)
+        .await?;
+    let (title, videos) = select_videos(
+        analysis,
+        request.segment_ids.as_deref(),
+        request.segment_output != SegmentOutput::Combined,
+    )?;
 
     client_ref.send(StateUpdate::Title(title.clone()));
+    let segment_output = segment_output(&videos, request.segment_output);
 
     let mut dest_name = title
         .chars()
@@ -152,68 +454,139 @@ pub(super) async fn download(
     dest_name.push_str("_");
     dest_name.push_str(&id);
 
-    if let Some(segment_id) = request.url.segment_id() {
-        let url = extract_segment_url(&html, segment_id)?;
-        let dest_path = check_mp4_path(&request.dest_dir, &dest_name).await?;
+    let mut media = fetch_media_urls(http_client, &videos, request.quality).await?;
+    let estimated_size = media.iter().map(MediaUrls::estimated_size).sum();
+    log::debug!("estimated size: {} bytes", estimated_size);
+
+    let work_dir = settings.temp_dir.as_deref().unwrap_or(&request.dest_dir);
+    fs::create_dir_all(work_dir).await?;
+    // Without pipelining the streams and the merged file exist side by side, segmented videos
+    // are kept until they have been concatenated or copied.
+    let work_factor =
+        if settings.pipelined && media.len() == 1 && segment_output != SegmentOutput::Both {
+            1
+        } else {
+            2
+        };
+    check_free_space(work_dir, &request.dest_dir, estimated_size, work_factor)?;
+
+    let temp_dir = TempDir::new_in(work_dir)?;
+    let out_path = temp_dir.path().join("output.mp4");
+    // The downloaded segments, for saving them separately.
+    let mut segment_paths = vec![];
+    // Folder with the separately saved segments and their total size.
+    let mut segments_dir = None;
+    let mut problems = vec![];
+
+    if media.len() == 1 {
         client_ref.send(StateUpdate::StartedVideo {
             video_no: 1,
             total_videos: 1,
         });
-        download_video(http_client, client_ref, url, request.quality, &request.dest_dir, &dest_path).await?;
-    } else {
-        let dest_path = check_mp4_path(&request.dest_dir, &dest_name).await?;
-        match extract_video_info(&html)? {
-            Unsegmented(mpd_url) => {
-                client_ref.send(StateUpdate::StartedVideo {
-                    video_no: 1,
-                    total_videos: 1,
-                });
-                download_video(http_client, client_ref, mpd_url, request.quality, &request.dest_dir, &dest_path)
-                    .await?;
+        let m = media.pop().unwrap();
+        let duration = m.duration;
+        download_video(http_client, client_ref, &ffmpeg, settings, m, &out_path).await?;
+        check_file(&out_path, duration, &mut problems).await?;
+        match segment_output {
+            SegmentOutput::Combined => {}
+            SegmentOutput::Separate => segment_paths.push(out_path.clone()),
+            SegmentOutput::Both => {
+                let seg_dest_path = temp_dir.path().join("0.mp4");
+                fs::copy(&out_path, &seg_dest_path).await?;
+                segment_paths.push(seg_dest_path);
             }
-            Segmented(mpd_urls) => {
-                let temp_dir = TempDir::new_in(&request.dest_dir)?;
-                let total_videos = mpd_urls.len() as u16;
-                let mut concat_list = String::new();
-
-                for (idx, mpd_url) in mpd_urls.into_iter().enumerate() {
-                    let file_name = format!("{}.mp4", idx);
-                    let seg_dest_path = temp_dir.path().join(&file_name);
-                    client_ref.send(StateUpdate::StartedVideo {
-                        video_no: idx as u16 + 1,
-                        total_videos,
-                    });
-                    download_video(
-                        http_client,
-                        client_ref,
-                        mpd_url,
-                        request.quality,
-                        temp_dir.path(),
-                        &seg_dest_path,
-                    )
-                    .await?;
-                    concat_list.push_str(&format!("file '{}'\n", &file_name));
-                }
+        }
+    } else {
+        let total_videos = media.len() as u16;
+        let total_duration = media.iter().map(|m| m.duration).sum::<Duration>();
+        let mut concat_list = String::new();
+        let mut chapters = vec![];
+
+        for (idx, (m, video)) in media.into_iter().zip(&videos).enumerate() {
+            chapters.push((video.title.as_deref(), m.duration));
+            let duration = m.duration;
+            let file_name = format!("{}.mp4", idx);
+            let seg_dest_path = temp_dir.path().join(&file_name);
+            client_ref.send(StateUpdate::StartedVideo {
+                video_no: idx as u16 + 1,
+                total_videos,
+            });
+            download_video(
+                http_client,
+                client_ref,
+                &ffmpeg,
+                settings,
+                m,
+                &seg_dest_path,
+            )
+            .await?;
+            check_file(&seg_dest_path, duration, &mut problems).await?;
+            concat_list.push_str(&format!("file '{}'\n", &file_name));
+            segment_paths.push(seg_dest_path);
+        }
 
-                fs::write(temp_dir.path().join("concat.txt"), concat_list).await?;
-                client_ref.send(StateUpdate::Merging);
-                run_ffmpeg(
+        if segment_output != SegmentOutput::Separate {
+            fs::write(temp_dir.path().join("concat.txt"), concat_list).await?;
+            fs::write(
+                temp_dir.path().join("chapters.txt"),
+                chapters_metadata(&chapters),
+            )
+            .await?;
+            client_ref.send(StateUpdate::Merging);
+            ffmpeg
+                .run(
                     &[
                         OsStr::new("-f"),
                         OsStr::new("concat"),
                         OsStr::new("-i"),
                         OsStr::new("concat.txt"),
+                        OsStr::new("-i"),
+                        OsStr::new("chapters.txt"),
+                        OsStr::new("-map_chapters"),
+                        OsStr::new("1"),
                         OsStr::new("-codec"),
                         OsStr::new("copy"),
-                        dest_path.as_os_str(),
+                        out_path.as_os_str(),
                     ],
                     Some(temp_dir.path()),
                 )
                 .await?;
-            }
+            check_file(&out_path, total_duration, &mut problems).await?;
         }
     }
-    Ok(())
+
+    if segment_output != SegmentOutput::Combined {
+        let saved = save_segments(&segment_paths, &videos, &request.dest_dir, &dest_name).await?;
+        log::info!("saved {} segments in {:?}", segment_paths.len(), saved.0);
+        segments_dir = Some(saved);
+    }
+
+    let (dest_path, size) = match segments_dir {
+        Some(saved) if segment_output == SegmentOutput::Separate => saved,
+        _ => {
+            let dest_path = check_mp4_path(&request.dest_dir, &dest_name).await?;
+            move_file(&out_path, &dest_path).await?;
+            let size = fs::metadata(&dest_path).await?.len();
+            (dest_path, size)
+        }
+    };
+    if let Some(error) = problems.into_iter().next() {
+        client_ref.send(StateUpdate::VerificationFailed(VerifyWarning {
+            title: title.clone(),
+            path: dest_path.clone(),
+            error,
+        }));
+    }
+    Ok(HistoryEntry {
+        video_id: id,
+        segment_id: request.url.segment_id().clone(),
+        segment_ids: request.segment_ids.clone(),
+        title,
+        quality: Some(request.quality),
+        path: Some(dest_path),
+        size: Some(size),
+        date: Local::now(),
+    })
 }
 
 #[cfg(test)]
@@ -221,7 +594,10 @@ mod tests {
     use std::fs::File;
 
     use tempfile::TempDir;
+    use url::Url;
 
+    use super::super::test_server::mp4_box;
+    use super::super::VideoUrl;
     use super::*;
 
     #[tokio::test]
@@ -265,4 +641,175 @@ mod tests {
             std::io::ErrorKind::AlreadyExists
         );
     }
+
+    #[tokio::test]
+    async fn test_move_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("from.mp4");
+        let to = temp_dir.path().join("to.mp4");
+        std::fs::write(&from, "foo").unwrap();
+
+        move_file(&from, &to).await.unwrap();
+        assert!(!from.exists());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "foo");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_free_space() {
+        let temp_dir = TempDir::new().unwrap();
+
+        assert!(check_free_space(temp_dir.path(), temp_dir.path(), 1024, 2).is_ok());
+        let res = check_free_space(temp_dir.path(), temp_dir.path(), u64::MAX / 2, 2);
+        assert!(matches!(res, Err(Error::DiskSpaceError { .. })));
+    }
+
+    #[test]
+    fn test_segment_file_name() {
+        assert_eq!(
+            segment_file_name(0, 12, Some("Signation")),
+            "01 - Signation.mp4"
+        );
+        assert_eq!(segment_file_name(2, 3, None), "03 - Teil 3.mp4");
+        assert_eq!(
+            segment_file_name(9, 120, Some("Wahl: Ergebnis/Analyse ")),
+            "010 - Wahl_ Ergebnis_Analyse.mp4"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_file_keeps_going() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("video.mp4");
+        fs::write(&path, mp4_box("ftyp", b"isom")).await.unwrap();
+
+        let mut problems = vec![];
+        check_file(&path, Duration::from_secs(10), &mut problems)
+            .await
+            .unwrap();
+        assert_eq!(problems, vec![VerifyError::MissingMovie]);
+
+        let missing = temp_dir.path().join("missing.mp4");
+        assert!(check_file(&missing, Duration::from_secs(10), &mut problems)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_chapters_metadata() {
+        let chapters = [
+            (Some("Signation"), Duration::from_secs(10)),
+            (None, Duration::from_millis(61500)),
+            (Some("Wetter; 1=1 "), Duration::from_secs(90)),
+        ];
+        assert_eq!(
+            chapters_metadata(&chapters),
+            ";FFMETADATA1\n\
+            \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=10000\ntitle=Signation\n\
+            \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=10000\nEND=71500\ntitle=Teil 2\n\
+            \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=71500\nEND=161500\ntitle=Wetter\\; 1\\=1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_path_dir_exists() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("foo")).unwrap();
+
+        let p = check_path(temp_dir.path(), "foo", "").await.unwrap();
+        assert_eq!(p.file_name().unwrap(), "foo_(1)");
+    }
+
+    #[test]
+    fn test_segment_output() {
+        let video = |segment_id: Option<&str>| Video {
+            segment_id: segment_id.map(str::to_owned),
+            title: None,
+            duration: None,
+            mpd_url: Url::parse("https://apasfiis.sf.apa.at/dash/a.mp4/manifest.mpd").unwrap(),
+        };
+        assert_eq!(
+            segment_output(&[video(None)], SegmentOutput::Both),
+            SegmentOutput::Combined
+        );
+        assert_eq!(
+            segment_output(&[video(Some("1"))], SegmentOutput::Separate),
+            SegmentOutput::Separate
+        );
+        assert_eq!(
+            segment_output(&[video(Some("1")), video(Some("2"))], SegmentOutput::Both),
+            SegmentOutput::Both
+        );
+    }
+
+    #[test]
+    fn test_select_videos() {
+        let video = |segment_id: Option<&str>| Video {
+            segment_id: segment_id.map(str::to_owned),
+            title: None,
+            duration: None,
+            mpd_url: Url::parse(
+                "https://apasfiis.sf.apa.at/dash/cms-worldwide/online/a_QXB.mp4/manifest.mpd",
+            )
+            .unwrap(),
+        };
+        let analysis = || Analysis {
+            title: "ZIB 1".to_owned(),
+            videos: vec![video(None)],
+            segments: vec![video(Some("1")), video(Some("2")), video(Some("3"))],
+        };
+
+        let (title, videos) = select_videos(analysis(), None, false).unwrap();
+        assert_eq!(title, "ZIB 1");
+        assert_eq!(videos, vec![video(None)]);
+        let (_, videos) = select_videos(analysis(), None, true).unwrap();
+        assert_eq!(videos.len(), 3);
+
+        let ids = ["3".to_owned(), "1".to_owned()];
+        let (_, videos) = select_videos(analysis(), Some(&ids), false).unwrap();
+        assert_eq!(videos, vec![video(Some("1")), video(Some("3"))]);
+
+        let ids = ["4".to_owned()];
+        assert!(select_videos(analysis(), Some(&ids), false).is_err());
+    }
+
+    #[test]
+    fn test_detect_geo_block() {
+        let austria = Url::parse(
+            "https://apasfiis.sf.apa.at/dash/cms-austria/online/1_1_QXB.mp4/manifest.mpd",
+        )
+        .unwrap();
+        let worldwide = Url::parse(
+            "https://apasfiis.sf.apa.at/dash/cms-worldwide/online/1_1_QXB.mp4/manifest.mpd",
+        )
+        .unwrap();
+        let extractor = VideoUrl::new("https://on.orf.at/video/14225330")
+            .unwrap()
+            .extractor();
+
+        let e = detect_geo_block(
+            extractor,
+            Error::HttpStatusError {
+                url: austria.clone(),
+                status: 403,
+            },
+        );
+        assert!(matches!(e, Error::GeoBlockedError { url } if url == austria));
+        let e = detect_geo_block(
+            extractor,
+            Error::HttpStatusError {
+                url: austria,
+                status: 404,
+            },
+        );
+        assert!(matches!(e, Error::HttpStatusError { status: 404, .. }));
+        let e = detect_geo_block(
+            extractor,
+            Error::HttpStatusError {
+                url: worldwide,
+                status: 403,
+            },
+        );
+        assert!(matches!(e, Error::HttpStatusError { status: 403, .. }));
+    }
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::{anyhow, bail, ensure, Context};
 use roxmltree::{Document, Node};
 use url::Url;
@@ -96,41 +98,93 @@ struct Segment {
 pub struct MediaUrls {
     pub video: Vec<Url>,
     pub audio: Vec<Url>,
+    pub duration: Duration,
+    /// Bandwidth of the chosen video representation in bits per second.
+    pub video_bandwidth: u64,
+    /// Bandwidth of the chosen audio representation in bits per second.
+    pub audio_bandwidth: u64,
+}
+
+impl MediaUrls {
+    pub fn estimated_video_size(&self) -> u64 {
+        self.video_bandwidth * self.duration.as_millis() as u64 / 8000
+    }
+
+    pub fn estimated_audio_size(&self) -> u64 {
+        self.audio_bandwidth * self.duration.as_millis() as u64 / 8000
+    }
+
+    pub fn estimated_size(&self) -> u64 {
+        self.estimated_video_size() + self.estimated_audio_size()
+    }
+}
+
+struct Stream {
+    urls: Vec<Url>,
+    bandwidth: u32,
+    duration: Duration,
+}
+
+/// Parses an ISO 8601 duration like `PT1H2M3.5S`, as used by `mediaPresentationDuration`.
+fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let time = s
+        .strip_prefix("PT")
+        .ok_or_else(|| anyhow!("unsupported duration: {}", s))?;
+    let mut secs = 0_f64;
+    let mut number = String::new();
+    for c in time.chars() {
+        let factor = match c {
+            'H' => 3600_f64,
+            'M' => 60_f64,
+            'S' => 1_f64,
+            _ => {
+                number.push(c);
+                continue;
+            }
+        };
+        secs += number
+            .parse::<f64>()
+            .with_context(|| format!("invalid duration: {}", s))?
+            * factor;
+        number.clear();
+    }
+    ensure!(number.is_empty(), "invalid duration: {}", s);
+
+    Ok(Duration::from_secs_f64(secs))
 }
 
 fn node_not_found(name: &'static str) -> anyhow::Error {
     anyhow!("node not found: {}", name)
 }
 
-fn urls_from_adaptation_set(
+fn stream_from_adaptation_set(
     base_url: &Url,
     as_node: Node,
     maybe_quality: Option<Quality>,
-) -> anyhow::Result<Vec<Url>> {
-    let representation_id = if let Some(quality) = maybe_quality {
-        let representations = as_node
-            .children()
-            .filter(|c| c.has_tag_name("Representation"))
-            .map(|n| {
-                let id = n
-                    .attribute("id")
-                    .ok_or_else(|| node_not_found("Representation[@id]"))?;
-                let bandwith = n
-                    .attribute("bandwidth")
-                    .ok_or_else(|| node_not_found(""))?
-                    .parse::<u32>()
-                    .context("could not parse bandwidth")?;
-
-                Ok((id, bandwith))
-            })
-            .collect::<anyhow::Result<Vec<(&str, u32)>>>()?;
-        ensure!(!representations.is_empty(), "no representation nodes found");
+) -> anyhow::Result<Stream> {
+    let representations = as_node
+        .children()
+        .filter(|c| c.has_tag_name("Representation"))
+        .map(|n| {
+            let id = n
+                .attribute("id")
+                .ok_or_else(|| node_not_found("Representation[@id]"))?;
+            let bandwith = n
+                .attribute("bandwidth")
+                .ok_or_else(|| node_not_found("Representation[@bandwidth]"))?
+                .parse::<u32>()
+                .context("could not parse bandwidth")?;
+
+            Ok((id, bandwith))
+        })
+        .collect::<anyhow::Result<Vec<(&str, u32)>>>()?;
+    ensure!(!representations.is_empty(), "no representation nodes found");
 
+    let (representation_id, bandwidth) = if let Some(quality) = maybe_quality {
         match quality {
             Quality::Low => representations
                 .iter()
                 .min_by_key(|(_, bandwidth)| bandwidth)
-                .map(|(id, _)| *id)
                 .unwrap(),
             Quality::Medium => {
                 let avg_bandwith = representations
@@ -141,28 +195,26 @@ fn urls_from_adaptation_set(
                 representations
                     .iter()
                     .min_by_key(|(_, bandwidth)| avg_bandwith.abs_diff(*bandwidth))
-                    .map(|(id, _)| *id)
                     .unwrap()
             }
             Quality::High => representations
                 .iter()
                 .max_by_key(|(_, bandwidth)| bandwidth)
-                .map(|(id, _)| *id)
                 .unwrap(),
         }
     } else {
-        as_node
-            .children()
-            .find(|c| c.has_tag_name("Representation"))
-            .ok_or_else(|| node_not_found("Representation"))?
-            .attribute("id")
-            .ok_or_else(|| node_not_found("Representation[@id]"))?
+        &representations[0]
     };
 
     let segment_template = as_node
         .children()
         .find(|c| c.has_tag_name("SegmentTemplate"))
         .ok_or_else(|| node_not_found("SegmentTemplate"))?;
+    let timescale = segment_template
+        .attribute("timescale")
+        .map(|t| t.parse::<u64>().context("could not parse timescale"))
+        .transpose()?
+        .unwrap_or(1);
     let init_template = segment_template
         .attribute("initialization")
         .ok_or_else(|| node_not_found("SegmentTemplate[@intialization]"))?;
@@ -202,6 +254,7 @@ fn urls_from_adaptation_set(
 
     let init_seg_template = SegmentTemplate::new(base_url, init_template)?;
     let mut urls = vec![init_seg_template.render(representation_id, None)];
+    let mut total_duration = 0;
 
     let seg_template = SegmentTemplate::new(base_url, template)?;
     let mut last_end_time = 0;
@@ -218,14 +271,24 @@ fn urls_from_adaptation_set(
             let end_time = start_time + s.duration;
             start_time = end_time;
             last_end_time = end_time;
+            total_duration += s.duration;
         }
     }
 
-    Ok(urls)
+    Ok(Stream {
+        urls,
+        bandwidth: *bandwidth,
+        duration: Duration::from_secs_f64(total_duration as f64 / timescale as f64),
+    })
 }
 
 pub(super) fn get_urls(base_url: &Url, xml: &str, quality: Quality) -> anyhow::Result<MediaUrls> {
     let doc = Document::parse(xml)?;
+    let maybe_duration = doc
+        .root_element()
+        .attribute("mediaPresentationDuration")
+        .map(parse_duration)
+        .transpose()?;
     let period = doc
         .root_element()
         .children()
@@ -240,9 +303,15 @@ pub(super) fn get_urls(base_url: &Url, xml: &str, quality: Quality) -> anyhow::R
         .find(|c| c.attribute("mimeType") == Some("audio/mp4"))
         .ok_or_else(|| node_not_found("AdaptationSet[@mimeType=audio/mp4]"))?;
 
+    let video = stream_from_adaptation_set(base_url, video_as, Some(quality))?;
+    let audio = stream_from_adaptation_set(base_url, audio_as, None)?;
+
     Ok(MediaUrls {
-        video: urls_from_adaptation_set(base_url, video_as, Some(quality))?,
-        audio: urls_from_adaptation_set(base_url, audio_as, None)?,
+        duration: maybe_duration.unwrap_or(video.duration),
+        video_bandwidth: video.bandwidth as u64,
+        audio_bandwidth: audio.bandwidth as u64,
+        video: video.urls,
+        audio: audio.urls,
     })
 }
 
@@ -299,6 +368,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse_duration("PT1H2M3.5S").unwrap(),
+            Duration::from_secs_f64(3723.5)
+        );
+        assert_eq!(parse_duration("PT25M").unwrap(), Duration::from_secs(1500));
+        assert!(parse_duration("P1D").is_err());
+        assert!(parse_duration("PT12").is_err());
+    }
+
+    #[test]
+    fn test_estimated_size() {
+        let m = MediaUrls {
+            video: vec![],
+            audio: vec![],
+            duration: Duration::from_secs(60),
+            video_bandwidth: 3_072_000,
+            audio_bandwidth: 128_000,
+        };
+        assert_eq!(m.estimated_video_size(), 23_040_000);
+        assert_eq!(m.estimated_size(), 24_000_000);
+    }
+
     fn get_test_mpd() -> (Url, String) {
         let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "test_files", "manifest.mpd"]
             .iter()
@@ -0,0 +1,111 @@
+use std::path::Path;
+use std::time::Duration;
+
+use super::super::mp4::{parse_tracks, read_moov, BoxError, Track, TrackKind};
+use super::super::Error;
+
+const DURATION_TOLERANCE: Duration = Duration::from_secs(2);
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("no movie box found")]
+    MissingMovie,
+    #[error("invalid movie box: {0}")]
+    InvalidMovie(#[from] BoxError),
+    #[error("{0} track missing")]
+    MissingTrack(TrackKind),
+    #[error("{kind} track is {actual:?} long, expected {expected:?}")]
+    Duration {
+        kind: TrackKind,
+        expected: Duration,
+        actual: Duration,
+    },
+}
+
+fn check_tracks(tracks: &[Track], expected: Duration) -> Result<(), VerifyError> {
+    let tolerance = DURATION_TOLERANCE.max(expected / 100);
+    for kind in [TrackKind::Video, TrackKind::Audio] {
+        let track = tracks
+            .iter()
+            .find(|t| t.kind == kind)
+            .ok_or(VerifyError::MissingTrack(kind))?;
+        if track.duration.abs_diff(expected) > tolerance {
+            return Err(VerifyError::Duration {
+                kind,
+                expected,
+                actual: track.duration,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the file at `path` contains a video and an audio track of the expected duration.
+pub async fn verify(path: &Path, expected: Duration) -> Result<(), Error> {
+    let moov = read_moov(path).await?.ok_or(VerifyError::MissingMovie)?;
+    let tracks = parse_tracks(&moov).map_err(VerifyError::from)?;
+    check_tracks(&tracks, expected)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::test_server::{movie, mp4_box};
+    use super::*;
+
+    fn track(kind: TrackKind, secs: u64) -> Track {
+        Track {
+            kind,
+            duration: Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn test_check_tracks() {
+        let expected = Duration::from_secs(600);
+        let tracks = [track(TrackKind::Video, 600), track(TrackKind::Audio, 604)];
+        assert_eq!(check_tracks(&tracks, expected), Ok(()));
+
+        let tracks = [track(TrackKind::Video, 600), track(TrackKind::Audio, 300)];
+        assert_eq!(
+            check_tracks(&tracks, expected),
+            Err(VerifyError::Duration {
+                kind: TrackKind::Audio,
+                expected,
+                actual: Duration::from_secs(300)
+            })
+        );
+
+        let tracks = [track(TrackKind::Video, 600), track(TrackKind::Other, 600)];
+        assert_eq!(
+            check_tracks(&tracks, expected),
+            Err(VerifyError::MissingTrack(TrackKind::Audio))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.mp4");
+        let data = [
+            mp4_box("ftyp", b"isom"),
+            movie(&[(b"vide", 1_000, 10_000), (b"soun", 44_100, 441_000)]),
+            mp4_box("mdat", &[0; 100]),
+        ]
+        .concat();
+        tokio::fs::write(&path, &data).await.unwrap();
+        assert!(verify(&path, Duration::from_secs(10)).await.is_ok());
+        assert!(matches!(
+            verify(&path, Duration::from_secs(20)).await,
+            Err(Error::VerificationError(VerifyError::Duration { .. }))
+        ));
+
+        tokio::fs::write(&path, mp4_box("ftyp", b"isom"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            verify(&path, Duration::from_secs(10)).await,
+            Err(Error::VerificationError(VerifyError::MissingMovie))
+        ));
+    }
+}
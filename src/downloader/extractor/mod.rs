@@ -0,0 +1,97 @@
+//! Site specific discovery of the metadata and the streams of videos. Every supported site
+//! implements [`Extractor`] and is listed in [`EXTRACTORS`].
+
+mod orf_on;
+
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use url::Url;
+
+use self::orf_on::OrfOn;
+use super::http::HttpClient;
+use super::{Error, ShowEpisode, ShowUrl, VideoUrl};
+
+static EXTRACTORS: [&dyn Extractor; 1] = [&OrfOn];
+
+/// Ids of a video, parsed from its url.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VideoIds {
+    pub video_id: String,
+    /// Part of the video, if the url only refers to one.
+    pub segment_id: Option<String>,
+}
+
+/// One of the videos that make up a download, the whole episode or one of its segments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Video {
+    /// Id of the segment, `None` for a whole episode.
+    pub segment_id: Option<String>,
+    /// Title of the segment, if known.
+    pub title: Option<String>,
+    pub duration: Option<Duration>,
+    pub mpd_url: Url,
+}
+
+pub struct Analysis {
+    pub title: String,
+    /// Videos that make up the download, in order.
+    pub videos: Vec<Video>,
+    /// All segments of the episode, to download a selection of them. Empty if the episode is
+    /// not segmented or its segments are unknown.
+    pub segments: Vec<Video>,
+}
+
+pub trait Extractor: Send + Sync {
+    /// Name of the site, for logging.
+    fn name(&self) -> &'static str;
+
+    /// Parses a url of the site, `None` if it does not refer to a video the extractor supports.
+    fn parse_url(&self, url: &Url) -> Option<VideoIds>;
+
+    /// Fetches the metadata of a video and discovers its streams.
+    fn analyze<'a>(
+        &'a self,
+        http_client: &'a HttpClient,
+        url: &'a VideoUrl,
+    ) -> BoxFuture<'a, Result<Analysis, Error>>;
+
+    /// Parses the url of a show and returns its id, `None` if the url does not refer to a show
+    /// or the site has none.
+    fn parse_show_url(&self, _url: &Url) -> Option<String> {
+        None
+    }
+
+    /// Lists the currently available episodes of a show.
+    fn list_episodes<'a>(
+        &'a self,
+        _http_client: &'a HttpClient,
+        _url: &'a ShowUrl,
+    ) -> BoxFuture<'a, Result<Vec<ShowEpisode>, Error>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+
+    /// Whether access to `url` is denied because of the location of the client, if it is
+    /// refused.
+    fn is_geo_restricted(&self, _url: &Url) -> bool {
+        false
+    }
+}
+
+pub fn load_rules() {
+    orf_on::load_rules();
+}
+
+/// Finds the extractor responsible for the url of a show, returning it with the show id.
+pub fn find_show(url: &Url) -> Option<(&'static dyn Extractor, String)> {
+    EXTRACTORS
+        .iter()
+        .find_map(|e| e.parse_show_url(url).map(|id| (*e, id)))
+}
+
+/// Finds the extractor responsible for a url.
+pub fn find(url: &Url) -> Option<(&'static dyn Extractor, VideoIds)> {
+    EXTRACTORS
+        .iter()
+        .find_map(|e| e.parse_url(url).map(|ids| (*e, ids)))
+}
@@ -0,0 +1,245 @@
+//! Video information from the JSON API of ORF On, which is more stable than the markup of the
+//! episode pages.
+
+use anyhow::Context;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use url::Url;
+
+use super::episode::Episode;
+use crate::downloader::http::{HttpClient, Response};
+use crate::downloader::Error;
+
+pub(super) const API_BASE_URL: &str = "https://api-tvthek.orf.at/api/v4.3/";
+/// The public episode endpoint expects the id prefixed with this string and base64 encoded.
+const EPISODE_ID_PREFIX: &str = "3dSlfek03nsLKdj4Jsd";
+const PROFILE_PAGE_SIZE: usize = 100;
+/// Limits the pages fetched of a list, in case the API keeps linking to further pages.
+const MAX_PAGES: usize = 20;
+
+fn episode_url(api_base_url: &Url, video_id: &str) -> Url {
+    let encrypted_id = BASE64_STANDARD.encode(format!("{}{}", EPISODE_ID_PREFIX, video_id));
+    api_base_url
+        .join(&format!("public/episode/encrypted/{}", encrypted_id))
+        .unwrap()
+}
+
+/// Fetches an episode from the API.
+pub(super) async fn fetch_episode(
+    http_client: &HttpClient,
+    api_base_url: &Url,
+    video_id: &str,
+) -> Result<Episode, Error> {
+    let Response { body, final_url } = http_client.get(episode_url(api_base_url, video_id)).await?;
+    Episode::from_json(&body)
+        .context("invalid episode")
+        .map_err(|source| Error::ScrapingError {
+            url: final_url,
+            source,
+        })
+}
+
+/// Fetches the episodes of a show that are currently available, newest first.
+pub(super) async fn fetch_profile_episodes(
+    http_client: &HttpClient,
+    api_base_url: &Url,
+    profile_id: &str,
+) -> Result<Vec<Episode>, Error> {
+    let mut episodes = vec![];
+    let mut next_url = Some(
+        api_base_url
+            .join(&format!(
+                "profile/{}/episodes?limit={}",
+                profile_id, PROFILE_PAGE_SIZE
+            ))
+            .unwrap(),
+    );
+    for _ in 0..MAX_PAGES {
+        let Some(url) = next_url.take() else {
+            break;
+        };
+        let Response { body, final_url } = http_client.get(url).await?;
+        let (page, next) = Episode::list_from_json(&body)
+            .context("invalid episode list")
+            .map_err(|source| Error::ScrapingError {
+                url: final_url.clone(),
+                source,
+            })?;
+        episodes.extend(page);
+        next_url = next.and_then(|href| final_url.join(&href).ok());
+    }
+    Ok(episodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use chrono::NaiveTime;
+
+    use super::*;
+    use crate::downloader::rate_limit::RateLimiter;
+    use crate::downloader::test_server::{Route, TestServer};
+    use crate::downloader::Settings;
+
+    const UNSEGMENTED_PATH: &str = "/public/episode/encrypted/M2RTbGZlazAzbnNMS2RqNEpzZDE0MjI0NTQ1";
+    const SEGMENTED_PATH: &str = "/public/episode/encrypted/M2RTbGZlazAzbnNMS2RqNEpzZDE0MjI1NjUx";
+
+    fn get_test_response(file_name: &str) -> String {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "test_files", "api", file_name]
+            .iter()
+            .collect();
+        read_to_string(path).unwrap()
+    }
+
+    async fn start_server() -> TestServer {
+        TestServer::start(vec![
+            (
+                UNSEGMENTED_PATH,
+                Route::ok(
+                    "application/json",
+                    get_test_response("episode_unsegmented.json"),
+                ),
+            ),
+            (
+                SEGMENTED_PATH,
+                Route::ok(
+                    "application/json",
+                    get_test_response("episode_segmented.json"),
+                ),
+            ),
+        ])
+        .await
+    }
+
+    async fn fetch(server: &TestServer, video_id: &str) -> Result<Episode, Error> {
+        let settings = Settings::default();
+        let http_client =
+            HttpClient::new(&settings, Arc::new(RateLimiter::new(&settings))).unwrap();
+        fetch_episode(&http_client, &server.url("/"), video_id).await
+    }
+
+    #[test]
+    fn test_episode_url() {
+        let base = Url::parse(API_BASE_URL).unwrap();
+        assert_eq!(
+            episode_url(&base, "14224545").as_str(),
+            format!("https://api-tvthek.orf.at/api/v4.3{}", UNSEGMENTED_PATH)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_unsegmented() {
+        let server = start_server().await;
+        let episode = fetch(&server, "14224545").await.unwrap();
+        assert_eq!(
+            episode.title,
+            "Willkommen Österreich mit Stermann & Grissemann"
+        );
+        assert!(episode.segments.is_empty());
+        let videos = episode.videos(None).unwrap();
+        assert_eq!(videos.len(), 1);
+        assert!(videos[0].mpd_url.path().starts_with("/dash/cms-worldwide/"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_segmented() {
+        let server = start_server().await;
+        let episode = fetch(&server, "14225651").await.unwrap();
+        assert_eq!(episode.title, "Klingendes Österreich");
+        assert_eq!(episode.mpd_url, None);
+        let titles = episode
+            .segments
+            .iter()
+            .map(|s| (s.id.as_str(), s.title.as_str(), s.duration))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![
+                (
+                    "15636091",
+                    "Frühling im Zillertal",
+                    Some(Duration::from_secs(1495))
+                ),
+                (
+                    "15636092",
+                    "Gauder Fest im Tiroler Zillertal",
+                    Some(Duration::from_secs(1365))
+                ),
+            ]
+        );
+        assert_eq!(episode.videos(None).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_profile_episodes() {
+        let server = TestServer::start(vec![
+            (
+                "/profile/13886795/episodes?limit=100",
+                Route::ok(
+                    "application/json",
+                    get_test_response("profile_episodes_1.json"),
+                ),
+            ),
+            (
+                "/profile/13886795/episodes?page=2&limit=100",
+                Route::ok(
+                    "application/json",
+                    get_test_response("profile_episodes_2.json"),
+                ),
+            ),
+        ])
+        .await;
+        let settings = Settings::default();
+        let http_client =
+            HttpClient::new(&settings, Arc::new(RateLimiter::new(&settings))).unwrap();
+        let episodes = fetch_profile_episodes(&http_client, &server.url("/"), "13886795")
+            .await
+            .unwrap();
+
+        let ids = episodes.iter().map(|e| e.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["14226001", "14225824", "14225650"]);
+        assert_eq!(episodes[0].title, "ZIB 1 vom 24.04.2024");
+        assert_eq!(
+            episodes[0].date.unwrap().to_rfc3339(),
+            "2024-04-24T19:30:00+02:00"
+        );
+        assert_eq!(episodes[0].duration, Some(Duration::from_secs(1097)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_youth_protected() {
+        let episode = r#"{
+            "id": 14224546,
+            "title": "Tatort",
+            "has_youth_protection": true,
+            "youth_protection_type": "22-06",
+            "sources": {}
+        }"#;
+        let server = TestServer::start(vec![(
+            "/public/episode/encrypted/M2RTbGZlazAzbnNMS2RqNEpzZDE0MjI0NTQ2",
+            Route::ok("application/json", episode),
+        )])
+        .await;
+        let episode = fetch(&server, "14224546").await.unwrap();
+        let window = episode.youth_protection.unwrap();
+        assert_eq!(window.start, NaiveTime::from_hms_opt(22, 0, 0).unwrap());
+        assert_eq!(window.end, NaiveTime::from_hms_opt(6, 0, 0).unwrap());
+
+        let server = start_server().await;
+        let episode = fetch(&server, "14224545").await.unwrap();
+        assert_eq!(episode.youth_protection, None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_unknown_episode() {
+        let server = start_server().await;
+        let res = fetch(&server, "1").await;
+        assert!(matches!(
+            res,
+            Err(Error::HttpStatusError { status: 404, .. })
+        ));
+    }
+}
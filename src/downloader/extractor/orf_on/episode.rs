@@ -0,0 +1,349 @@
+//! Typed model of an episode, as delivered by the API and embedded in the episode pages.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, FixedOffset};
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use super::extract::{prefer_worldwide, youth_protection_window};
+use crate::downloader::extractor::Video;
+use crate::downloader::TimeWindow;
+
+/// Quality key of the adaptive stream, whose manifest offers all qualities.
+const ADAPTIVE_QUALITY_KEY: &str = "QXB";
+
+#[derive(Deserialize, Debug)]
+struct SourceJson {
+    quality_key: String,
+    src: Url,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct SourcesJson {
+    #[serde(default)]
+    dash: Vec<SourceJson>,
+}
+
+impl SourcesJson {
+    fn mpd_url(&self) -> Option<Url> {
+        let urls = self
+            .dash
+            .iter()
+            .filter(|s| s.quality_key == ADAPTIVE_QUALITY_KEY)
+            .map(|s| s.src.clone());
+        prefer_worldwide(urls).into_iter().next()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct SegmentJson {
+    id: u64,
+    #[serde(default)]
+    title: String,
+    duration_seconds: Option<f64>,
+    #[serde(default)]
+    sources: SourcesJson,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct EmbeddedJson {
+    #[serde(default)]
+    segments: Vec<SegmentJson>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EpisodeJson {
+    id: u64,
+    title: String,
+    date: Option<DateTime<FixedOffset>>,
+    duration_seconds: Option<f64>,
+    #[serde(default)]
+    has_youth_protection: bool,
+    youth_protection_type: Option<String>,
+    #[serde(default)]
+    sources: SourcesJson,
+    #[serde(default, rename = "_embedded")]
+    embedded: EmbeddedJson,
+}
+
+#[derive(Deserialize, Debug)]
+struct LinkJson {
+    href: String,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct LinksJson {
+    next: Option<LinkJson>,
+}
+
+/// A page of a list of episodes.
+#[derive(Deserialize, Debug)]
+struct EpisodeListJson {
+    #[serde(default, rename = "_items")]
+    items: Vec<EpisodeJson>,
+    #[serde(default, rename = "_links")]
+    links: LinksJson,
+}
+
+fn duration(seconds: Option<f64>) -> Option<Duration> {
+    seconds
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct Segment {
+    pub id: String,
+    pub title: String,
+    pub duration: Option<Duration>,
+    pub mpd_url: Option<Url>,
+}
+
+impl Segment {
+    fn video(&self) -> Option<Video> {
+        Some(Video {
+            segment_id: Some(self.id.clone()),
+            title: Some(self.title.clone()),
+            duration: self.duration,
+            mpd_url: self.mpd_url.clone()?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct Episode {
+    pub id: String,
+    pub title: String,
+    /// Time of the broadcast.
+    pub date: Option<DateTime<FixedOffset>>,
+    pub duration: Option<Duration>,
+    /// Time of day the episode may be streamed in, if restricted because of youth protection.
+    pub youth_protection: Option<TimeWindow>,
+    /// Source of the whole episode, missing if it is only offered in segments.
+    pub mpd_url: Option<Url>,
+    pub segments: Vec<Segment>,
+}
+
+impl From<EpisodeJson> for Episode {
+    fn from(e: EpisodeJson) -> Self {
+        Self {
+            id: e.id.to_string(),
+            title: e.title,
+            date: e.date,
+            duration: duration(e.duration_seconds),
+            youth_protection: e
+                .has_youth_protection
+                .then(|| youth_protection_window(e.youth_protection_type.as_deref())),
+            mpd_url: e.sources.mpd_url(),
+            segments: e
+                .embedded
+                .segments
+                .into_iter()
+                .map(|s| Segment {
+                    id: s.id.to_string(),
+                    title: s.title,
+                    duration: duration(s.duration_seconds),
+                    mpd_url: s.sources.mpd_url(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Episode {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str::<EpisodeJson>(json).map(Self::from)
+    }
+
+    /// Parses a page of an episode list, returning its episodes and the link to the next page.
+    pub fn list_from_json(json: &str) -> serde_json::Result<(Vec<Self>, Option<String>)> {
+        let list = serde_json::from_str::<EpisodeListJson>(json)?;
+        Ok((
+            list.items.into_iter().map(Self::from).collect(),
+            list.links.next.map(|l| l.href),
+        ))
+    }
+
+    /// Searches a JSON document, like the state embedded in a page, for the episode with the id.
+    pub fn find(value: &Value, id: &str) -> Option<Self> {
+        match value {
+            Value::Object(map) => {
+                let id_matches = match map.get("id") {
+                    Some(Value::Number(n)) => n.to_string() == id,
+                    Some(Value::String(s)) => s == id,
+                    _ => false,
+                };
+                if id_matches && (map.contains_key("sources") || map.contains_key("_embedded")) {
+                    if let Ok(e) = EpisodeJson::deserialize(value) {
+                        return Some(e.into());
+                    }
+                }
+                map.values().find_map(|v| Self::find(v, id))
+            }
+            Value::Array(values) => values.iter().find_map(|v| Self::find(v, id)),
+            _ => None,
+        }
+    }
+
+    /// The segments that have a source.
+    pub fn segment_videos(&self) -> Vec<Video> {
+        self.segments.iter().filter_map(Segment::video).collect()
+    }
+
+    /// The videos of the whole episode, or of a single segment.
+    pub fn videos(&self, segment_id: Option<&str>) -> anyhow::Result<Vec<Video>> {
+        if let Some(segment_id) = segment_id {
+            let segment = self
+                .segments
+                .iter()
+                .find(|s| s.id == segment_id)
+                .context("segment not found")?;
+            return Ok(vec![segment.video().context("no source for segment")?]);
+        }
+        if let Some(url) = &self.mpd_url {
+            return Ok(vec![Video {
+                segment_id: None,
+                title: None,
+                duration: self.duration,
+                mpd_url: url.clone(),
+            }]);
+        }
+        self.segments
+            .iter()
+            .map(Segment::video)
+            .collect::<Option<Vec<_>>>()
+            .filter(|videos| !videos.is_empty())
+            .ok_or_else(|| anyhow!("no sources found"))
+    }
+
+    /// Title of the download, including the title of the segment if only one is requested.
+    pub fn title(&self, segment_id: Option<&str>) -> String {
+        match self
+            .segments
+            .iter()
+            .find(|s| Some(s.id.as_str()) == segment_id)
+        {
+            Some(segment) => format!("{} - {}", self.title, segment.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn source(url: &str) -> Value {
+        json!({ "dash": [{ "quality_key": "QXB", "src": url }] })
+    }
+
+    #[test]
+    fn test_find() {
+        let state = json!({
+            "page": { "id": 14225651, "type": "episode" },
+            "episodes": [{
+                "id": 14225651,
+                "title": "Klingendes Österreich",
+                "duration_seconds": 2860,
+                "sources": {},
+                "_embedded": { "segments": [
+                    {
+                        "id": 15636091,
+                        "title": "Frühling im Zillertal",
+                        "duration_seconds": 1495.5,
+                        "sources": source("https://apasfiis.sf.apa.at/dash/cms-worldwide/online/a/1.mp4/manifest.mpd")
+                    },
+                    { "id": 15636092, "title": "Gauder Fest", "sources": {} }
+                ]}
+            }]
+        });
+        let episode = Episode::find(&state, "14225651").unwrap();
+        assert_eq!(
+            episode,
+            Episode {
+                id: "14225651".to_owned(),
+                title: "Klingendes Österreich".to_owned(),
+                date: None,
+                duration: Some(Duration::from_secs(2860)),
+                youth_protection: None,
+                mpd_url: None,
+                segments: vec![
+                    Segment {
+                        id: "15636091".to_owned(),
+                        title: "Frühling im Zillertal".to_owned(),
+                        duration: Some(Duration::from_millis(1_495_500)),
+                        mpd_url: Some(
+                            Url::parse("https://apasfiis.sf.apa.at/dash/cms-worldwide/online/a/1.mp4/manifest.mpd")
+                                .unwrap()
+                        ),
+                    },
+                    Segment {
+                        id: "15636092".to_owned(),
+                        title: "Gauder Fest".to_owned(),
+                        duration: None,
+                        mpd_url: None,
+                    },
+                ],
+            }
+        );
+        assert!(Episode::find(&state, "1").is_none());
+    }
+
+    #[test]
+    fn test_videos() {
+        let url = |n| {
+            Url::parse(&format!(
+                "https://apasfiis.sf.apa.at/dash/cms-worldwide/online/a/{}.mp4/manifest.mpd",
+                n
+            ))
+            .unwrap()
+        };
+        let segment = |id: &str, mpd_url| Segment {
+            id: id.to_owned(),
+            title: format!("Teil {}", id),
+            duration: None,
+            mpd_url,
+        };
+        let mpd_urls =
+            |videos: Vec<Video>| videos.into_iter().map(|v| v.mpd_url).collect::<Vec<_>>();
+        let mut episode = Episode {
+            id: "1".to_owned(),
+            title: "Folge".to_owned(),
+            date: None,
+            duration: None,
+            youth_protection: None,
+            mpd_url: Some(url(0)),
+            segments: vec![segment("2", Some(url(2))), segment("3", Some(url(3)))],
+        };
+        assert_eq!(mpd_urls(episode.videos(None).unwrap()), vec![url(0)]);
+        assert_eq!(episode.segment_videos().len(), 2);
+        let videos = episode.videos(Some("3")).unwrap();
+        assert_eq!(
+            videos,
+            vec![Video {
+                segment_id: Some("3".to_owned()),
+                title: Some("Teil 3".to_owned()),
+                duration: None,
+                mpd_url: url(3),
+            }]
+        );
+        assert_eq!(episode.title(Some("3")), "Folge - Teil 3");
+        assert_eq!(episode.title(None), "Folge");
+        assert!(episode.videos(Some("4")).is_err());
+
+        episode.mpd_url = None;
+        assert_eq!(
+            mpd_urls(episode.videos(None).unwrap()),
+            vec![url(2), url(3)]
+        );
+
+        episode.segments[1].mpd_url = None;
+        assert!(episode.videos(None).is_err());
+        assert_eq!(episode.segment_videos().len(), 1);
+    }
+}
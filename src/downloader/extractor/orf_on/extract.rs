@@ -0,0 +1,378 @@
+use anyhow::{anyhow, ensure, Context, Ok};
+use chrono::NaiveTime;
+use html_escape::decode_html_entities;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use url::Url;
+
+use super::episode::Episode;
+use super::rules::RULES;
+use crate::downloader::TimeWindow;
+
+/// Window used if a page is marked as restricted without stating the times.
+const DEFAULT_YOUTH_PROTECTION_WINDOW: (u32, u32) = (20, 6);
+
+pub(super) fn extract_title(html: &str) -> anyhow::Result<String> {
+    Ok(decode_html_entities(
+        &RULES
+            .title
+            .captures(&html)
+            .context("could not extract title")?
+            .get(1)
+            .unwrap()
+            .as_str()
+            .to_owned(),
+    )
+    .into_owned())
+}
+
+/// Finds the episode in the JSON embedded in the page, like JSON-LD or the state of the app.
+pub(super) fn extract_episode(html: &str, video_id: &str) -> anyhow::Result<Episode> {
+    lazy_static! {
+        static ref JSON_SCRIPT_RE: Regex =
+            Regex::new(r#"(?s)<script[^>]*type="application/(?:ld\+)?json"[^>]*>(.*?)</script>"#)
+                .unwrap();
+        static ref STATE_SCRIPT_RE: Regex =
+            Regex::new(r"(?s)<script[^>]*>\s*window\.__[A-Z_]+__\s*=\s*(\{.*?\});?\s*</script>")
+                .unwrap();
+    }
+    JSON_SCRIPT_RE
+        .captures_iter(html)
+        .chain(STATE_SCRIPT_RE.captures_iter(html))
+        .filter_map(|c| serde_json::from_str::<Value>(&c[1]).ok())
+        .find_map(|v| Episode::find(&v, video_id))
+        .context("no episode found in the embedded json")
+}
+
+/// Collects the ids of the episodes linked from a page, like the profile page of a show.
+pub(super) fn extract_episode_ids(html: &str) -> Vec<String> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"href="(?:https?://on\.orf\.at)?/video/(?<video_id>[0-9]+)"#).unwrap();
+    }
+    let mut ids: Vec<String> = vec![];
+    for c in RE.captures_iter(html) {
+        if !ids.iter().any(|id| id == &c["video_id"]) {
+            ids.push(c["video_id"].to_owned());
+        }
+    }
+    ids
+}
+
+/// Detects videos that may only be streamed at night because of youth protection rules and
+/// returns the time window in which they are available.
+pub(super) fn extract_youth_protection(html: &str) -> Option<TimeWindow> {
+    lazy_static! {
+        static ref NOTICE_RE: Regex = Regex::new(
+            r"Jugendschutz[^<]{0,200}?(?<start>[0-9]{1,2})(?:[:.](?<start_min>[0-9]{2}))?\s*(?:Uhr\s*)?(?:und|bis|-|–)\s*(?<end>[0-9]{1,2})(?:[:.](?<end_min>[0-9]{2}))?\s*Uhr"
+        )
+        .unwrap();
+        static ref FLAG_RE: Regex = Regex::new(r#""has_youth_protection"\s*:\s*true"#).unwrap();
+    }
+    let time = |hour: &str, min: Option<&str>| {
+        NaiveTime::from_hms_opt(
+            hour.parse().ok()?,
+            min.map_or(Some(0), |m| m.parse().ok())?,
+            0,
+        )
+    };
+    if let Some(c) = NOTICE_RE.captures(html) {
+        let start = time(&c["start"], c.name("start_min").map(|m| m.as_str()));
+        let end = time(&c["end"], c.name("end_min").map(|m| m.as_str()));
+        if let (Some(start), Some(end)) = (start, end) {
+            return Some(TimeWindow { start, end });
+        }
+    }
+    if FLAG_RE.is_match(html) {
+        return Some(youth_protection_window(None));
+    }
+    None
+}
+
+/// The time window of a youth protection type of the API, like `"20-06"`. Types without hours
+/// get the usual window.
+pub(super) fn youth_protection_window(protection_type: Option<&str>) -> TimeWindow {
+    lazy_static! {
+        static ref HOURS_RE: Regex =
+            Regex::new(r"(?<start>[0-9]{1,2})[^0-9]+(?<end>[0-9]{1,2})").unwrap();
+    }
+    let hour = |h: &str| NaiveTime::from_hms_opt(h.parse().ok()?, 0, 0);
+    if let Some(c) = protection_type.and_then(|t| HOURS_RE.captures(t)) {
+        if let (Some(start), Some(end)) = (hour(&c["start"]), hour(&c["end"])) {
+            return TimeWindow { start, end };
+        }
+    }
+    let (start, end) = DEFAULT_YOUTH_PROTECTION_WINDOW;
+    TimeWindow {
+        start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+        end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+    }
+}
+
+/// Whether the url points to the CDN for content that may only be streamed in Austria.
+pub(super) fn is_austria_only(url: &Url) -> bool {
+    url.path().starts_with("/dash/cms-austria/")
+}
+
+/// Removes duplicate urls offered on different CDNs, preferring the worldwide one over the
+/// Austria-only one. The order of first appearance is kept.
+pub(super) fn prefer_worldwide(urls: impl IntoIterator<Item = Url>) -> Vec<Url> {
+    lazy_static! {
+        static ref CMS_RE: Regex = Regex::new(r"^/dash/cms-[a-z_]+/").unwrap();
+    }
+    let mut unique: Vec<(String, Url)> = vec![];
+    for url in urls {
+        let key = format!(
+            "{}{}",
+            url.host_str().unwrap_or_default(),
+            CMS_RE.replace(url.path(), "/dash/cms/")
+        );
+        match unique.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => {
+                if is_austria_only(existing) && !is_austria_only(&url) {
+                    *existing = url;
+                }
+            }
+            None => unique.push((key, url)),
+        }
+    }
+    unique.into_iter().map(|(_, url)| url).collect()
+}
+
+pub(super) fn extract_segment_url(html: &str, segment_id: &str) -> anyhow::Result<Url> {
+    let urls = RULES
+        .segment_mpd
+        .captures_iter(html)
+        .filter(|c| &c["segment_id"] == segment_id)
+        .filter_map(|c| Url::parse(&c[0]).ok());
+    prefer_worldwide(urls)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("could not extract segment url"))
+}
+
+/// Id of the segment a mpd-url belongs to, `None` for a whole episode.
+pub(super) fn segment_id_of(url: &Url) -> Option<String> {
+    RULES
+        .segment_mpd
+        .captures(url.as_str())
+        .map(|c| c["segment_id"].to_owned())
+}
+
+#[derive(Debug)]
+pub(super) enum VideoInfo {
+    Unsegmented(Url),
+    Segmented(Vec<Url>),
+}
+
+pub(super) fn extract_video_info(html: &str) -> anyhow::Result<VideoInfo> {
+    let unsegmented_urls = RULES
+        .unsegmented_mpd
+        .find_iter(html)
+        .filter_map(|m| Url::parse(m.as_str()).ok());
+    if let Some(url) = prefer_worldwide(unsegmented_urls).into_iter().next() {
+        Ok(VideoInfo::Unsegmented(url))
+    } else {
+        let urls = prefer_worldwide(
+            RULES
+                .mpd
+                .find_iter(&html)
+                .filter_map(|m| Url::parse(m.as_str()).ok()),
+        );
+        ensure!(!urls.is_empty(), "could not extract mpd-urls");
+
+        if urls.len() == 1 {
+            Ok(VideoInfo::Unsegmented(urls[0].clone()))
+        } else {
+            Ok(VideoInfo::Segmented(urls))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+    use std::path::PathBuf;
+
+    use insta::assert_debug_snapshot;
+
+    use super::*;
+
+    fn get_test_html(file_name: &str) -> String {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "test_files", file_name]
+            .iter()
+            .collect();
+        read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_extract_title() {
+        let title: String = extract_title(&get_test_html("title.html")).unwrap();
+        assert_eq!(title, "ZIB 1 vom 08.05.2024");
+    }
+
+    #[test]
+    fn test_extract_title_escaped() {
+        let title: String = extract_title(&get_test_html("title_escaped.html")).unwrap();
+        assert_eq!(title, "ORF-Hilfsaktion \"Österreich hilft Österreich\" - Wien heute vom 13.06.2024");
+    }
+
+    #[test]
+    fn test_extract_segmented() {
+        let u = extract_video_info(&get_test_html("segmented.html"));
+        assert_debug_snapshot!(u);
+    }
+
+    #[test]
+    fn test_extract_unsegmented() {
+        let u = extract_video_info(&get_test_html("unsegmented.html"));
+        assert_debug_snapshot!(u);
+    }
+
+    #[test]
+    fn test_extract_segmented_and_unsegmented() {
+        let u = extract_video_info(&get_test_html("segmented_and_unsegmented.html"));
+        assert_debug_snapshot!(u);
+    }
+
+    #[test]
+    fn test_extract_segment_url() {
+        let u = extract_segment_url(&get_test_html("segment.html"), "15658303");
+        assert_debug_snapshot!(u);
+    }
+
+    #[test]
+    fn test_extract_without_bumper_clip() {
+        let u = extract_video_info(&get_test_html("with_bumper_clip.html"));
+        assert_debug_snapshot!(u);
+    }
+
+    #[test]
+    fn test_extract_episode() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">{"@context":"https://schema.org","@type":"VideoObject","name":"Klingendes Österreich"}</script>
+            </head><body>
+            <video data-bumper="https://apasfiis.sf.apa.at/dash/cms-worldwide/online/bumper_QXB.mp4/manifest.mpd"></video>
+            <script>window.__INITIAL_STATE__ = {"episode":{"id":14225651,"title":"Klingendes Österreich","sources":{},"_embedded":{"segments":[
+                {"id":15636091,"title":"Frühling im Zillertal","duration_seconds":1495,"sources":{"dash":[{"quality_key":"QXB","src":"https://apasfiis.sf.apa.at/dash/cms-worldwide/online/a/1_QXB.mp4/manifest.mpd"}]}},
+                {"id":15636092,"title":"Gauder Fest","duration_seconds":1365,"sources":{"dash":[{"quality_key":"QXB","src":"https://apasfiis.sf.apa.at/dash/cms-worldwide/online/a/2_QXB.mp4/manifest.mpd"}]}}
+            ]}}};</script>
+            </body></html>"#;
+        let episode = extract_episode(html, "14225651").unwrap();
+        assert_eq!(episode.title, "Klingendes Österreich");
+        assert_eq!(episode.segments.len(), 2);
+        let videos = episode.videos(None).unwrap();
+        assert_eq!(videos.len(), 2);
+        assert!(videos.iter().all(|v| !v.mpd_url.path().contains("bumper")));
+
+        assert!(extract_episode(html, "14225652").is_err());
+        assert!(extract_episode("<html></html>", "14225651").is_err());
+    }
+
+    #[test]
+    fn test_segment_id_of() {
+        let url = |s| Url::parse(s).unwrap();
+        assert_eq!(
+            segment_id_of(&url("https://apasfiis.sf.apa.at/dash/cms-worldwide/online/a__s15636092_b_QXB.mp4/manifest.mpd")),
+            Some("15636092".to_owned())
+        );
+        assert_eq!(
+            segment_id_of(&url("https://apasfiis.sf.apa.at/dash/cms-worldwide/online/14225651_0016_QXB.mp4/manifest.mpd")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_episode_ids() {
+        let html = r#"<a href="/video/14226001/zib-1-vom-24042024">ZIB 1</a>
+            <a href="https://on.orf.at/video/14225824/zib-1-vom-23042024"><img></a>
+            <a href="/video/14225824/zib-1-vom-23042024">ZIB 1</a>
+            <a href="/profile/13886795/zib-1">ZIB 1</a>"#;
+        assert_eq!(extract_episode_ids(html), vec!["14226001", "14225824"]);
+    }
+
+    #[test]
+    fn test_extract_youth_protection() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let html = "<p>Dieses Video ist aus Jugendschutzgründen nur zwischen 22:00 und 5:30 Uhr verfügbar.</p>";
+        assert_eq!(
+            extract_youth_protection(html),
+            Some(TimeWindow {
+                start: time(22, 0),
+                end: time(5, 30)
+            })
+        );
+        let html = r#"<p>Aus Jugendschutzgründen von 20 bis 6 Uhr abrufbar</p>"#;
+        assert_eq!(
+            extract_youth_protection(html),
+            Some(TimeWindow {
+                start: time(20, 0),
+                end: time(6, 0)
+            })
+        );
+        let html = r#"<script>{"id":14224545,"has_youth_protection":true}</script>"#;
+        assert_eq!(
+            extract_youth_protection(html),
+            Some(TimeWindow {
+                start: time(20, 0),
+                end: time(6, 0)
+            })
+        );
+        let html = r#"<script>{"id":14224545,"has_youth_protection":false}</script>"#;
+        assert_eq!(extract_youth_protection(html), None);
+    }
+
+    #[test]
+    fn test_youth_protection_window() {
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        assert_eq!(
+            youth_protection_window(Some("22-05")),
+            TimeWindow {
+                start: time(22),
+                end: time(5)
+            }
+        );
+        let default = TimeWindow {
+            start: time(20),
+            end: time(6),
+        };
+        assert_eq!(youth_protection_window(Some("JS")), default);
+        assert_eq!(youth_protection_window(Some("30-99")), default);
+        assert_eq!(youth_protection_window(None), default);
+    }
+
+    #[test]
+    fn test_prefer_worldwide() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let austria_1 =
+            url("https://apasfiis.sf.apa.at/dash/cms-austria/online/1_1_QXB.mp4/manifest.mpd");
+        let worldwide_1 =
+            url("https://apasfiis.sf.apa.at/dash/cms-worldwide/online/1_1_QXB.mp4/manifest.mpd");
+        let austria_2 =
+            url("https://apasfiis.sf.apa.at/dash/cms-austria/online/2_2_QXB.mp4/manifest.mpd");
+
+        assert!(is_austria_only(&austria_1));
+        assert!(!is_austria_only(&worldwide_1));
+        assert_eq!(
+            prefer_worldwide([austria_1.clone(), austria_2.clone(), worldwide_1.clone()]),
+            vec![worldwide_1.clone(), austria_2.clone()]
+        );
+        assert_eq!(
+            prefer_worldwide([worldwide_1.clone(), austria_1, worldwide_1.clone()]),
+            vec![worldwide_1]
+        );
+    }
+
+    #[test]
+    fn test_extract_prefers_worldwide() {
+        let html = r#"<script>{"sources":[
+            {"src":"https://apasfiis.sf.apa.at/dash/cms-austria/online/14224545_0001_QXB.mp4/manifest.mpd"},
+            {"src":"https://apasfiis.sf.apa.at/dash/cms-worldwide/online/14224545_0001_QXB.mp4/manifest.mpd"}
+        ]}</script>"#;
+        match extract_video_info(html).unwrap() {
+            VideoInfo::Unsegmented(url) => assert!(!is_austria_only(&url)),
+            VideoInfo::Segmented(_) => panic!("expected unsegmented video"),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Extractor for ORF On (on.orf.at), using its JSON API and the episode pages as fallback.
+
+mod api;
+mod episode;
+mod extract;
+mod rules;
+
+use chrono::Local;
+use futures_util::future::BoxFuture;
+use lazy_static::lazy_static;
+use regex::Regex;
+use url::Url;
+
+use self::episode::Episode;
+use self::extract::VideoInfo::*;
+use self::extract::{
+    extract_episode, extract_episode_ids, extract_segment_url, extract_title, extract_video_info,
+    extract_youth_protection, is_austria_only, segment_id_of,
+};
+use super::{Analysis, Extractor, Video, VideoIds};
+use crate::downloader::http::{HttpClient, Response};
+use crate::downloader::{Error, ShowEpisode, ShowUrl, TimeWindow, VideoUrl};
+
+pub struct OrfOn;
+
+/// Loads the extraction rules, so errors in the rules file are reported right away.
+pub fn load_rules() {
+    lazy_static::initialize(&rules::RULES);
+}
+
+impl Extractor for OrfOn {
+    fn name(&self) -> &'static str {
+        "ORF On"
+    }
+
+    fn parse_url(&self, url: &Url) -> Option<VideoIds> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(
+                r"^https?://on\.orf\.at/video/(?<video_id>[0-9]+)(/(?<segment_id>[0-9]+))?(/.+)?$"
+            )
+            .unwrap();
+        }
+        let cap = RE.captures(url.as_str())?;
+        Some(VideoIds {
+            video_id: cap["video_id"].to_owned(),
+            segment_id: cap.name("segment_id").map(|s| s.as_str().to_owned()),
+        })
+    }
+
+    fn analyze<'a>(
+        &'a self,
+        http_client: &'a HttpClient,
+        url: &'a VideoUrl,
+    ) -> BoxFuture<'a, Result<Analysis, Error>> {
+        Box::pin(analyze(http_client, url))
+    }
+
+    fn parse_show_url(&self, url: &Url) -> Option<String> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"^https?://on\.orf\.at/profile/(?<profile_id>[0-9]+)(/.*)?$").unwrap();
+        }
+        Some(RE.captures(url.as_str())?["profile_id"].to_owned())
+    }
+
+    fn list_episodes<'a>(
+        &'a self,
+        http_client: &'a HttpClient,
+        url: &'a ShowUrl,
+    ) -> BoxFuture<'a, Result<Vec<ShowEpisode>, Error>> {
+        Box::pin(list_episodes(http_client, url))
+    }
+
+    fn is_geo_restricted(&self, url: &Url) -> bool {
+        is_austria_only(url)
+    }
+}
+
+fn show_episode(episode: Episode) -> Option<ShowEpisode> {
+    Some(ShowEpisode {
+        url: VideoUrl::new(&format!("https://on.orf.at/video/{}", episode.id)).ok()?,
+        title: episode.title,
+        date: episode.date.map(|d| d.with_timezone(&Local)),
+        duration: episode.duration,
+    })
+}
+
+/// Lists the episodes of a show from the API or, if that fails, from the episodes linked on its
+/// profile page.
+async fn list_episodes(http_client: &HttpClient, url: &ShowUrl) -> Result<Vec<ShowEpisode>, Error> {
+    let api_base_url = Url::parse(api::API_BASE_URL).unwrap();
+    match api::fetch_profile_episodes(http_client, &api_base_url, url.show_id()).await {
+        Ok(episodes) => return Ok(episodes.into_iter().filter_map(show_episode).collect()),
+        Err(e) => log::warn!("could not get the episodes from the API: {}", e),
+    }
+
+    let Response { body: html, .. } = http_client.get(url.as_ref().clone()).await?;
+    let mut episodes = vec![];
+    for id in extract_episode_ids(&html) {
+        let Ok(video_url) = VideoUrl::new(&format!("https://on.orf.at/video/{}", id)) else {
+            continue;
+        };
+        match api::fetch_episode(http_client, &api_base_url, &id).await {
+            Ok(episode) => episodes.extend(show_episode(episode)),
+            Err(e) => {
+                log::warn!("could not get episode {}: {}", id, e);
+                episodes.push(ShowEpisode {
+                    title: video_url.as_str().to_owned(),
+                    url: video_url,
+                    date: None,
+                    duration: None,
+                });
+            }
+        }
+    }
+    Ok(episodes)
+}
+
+/// Extracts the title and the videos of a request, from the API or, if that fails, from its
+/// page.
+async fn analyze(http_client: &HttpClient, url: &VideoUrl) -> Result<Analysis, Error> {
+    let api_base_url = Url::parse(api::API_BASE_URL).unwrap();
+    let segment_id = url.segment_id().as_deref();
+    match api::fetch_episode(http_client, &api_base_url, url.video_id()).await {
+        Ok(episode) => {
+            check_youth_protection(episode.youth_protection)?;
+            match episode.videos(segment_id) {
+                Ok(videos) => {
+                    log_episode(&episode);
+                    return Ok(Analysis {
+                        title: episode.title(segment_id),
+                        videos,
+                        segments: episode.segment_videos(),
+                    });
+                }
+                Err(e) => log::warn!("no usable sources in the API response: {:#}", e),
+            }
+        }
+        Err(e) => log::warn!("could not get video information from the API: {}", e),
+    }
+    scrape_page(http_client, url).await
+}
+
+/// Fails with the time window if the episode may not be streamed now.
+fn check_youth_protection(window: Option<TimeWindow>) -> Result<(), Error> {
+    match window {
+        Some(window) if !window.contains(Local::now().time()) => {
+            Err(Error::YouthProtectionError { window })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Video known only by its mpd-url, as matched in a page.
+fn video_of(mpd_url: Url) -> Video {
+    Video {
+        segment_id: segment_id_of(&mpd_url),
+        title: None,
+        duration: None,
+        mpd_url,
+    }
+}
+
+fn log_episode(episode: &Episode) {
+    log::debug!(
+        "episode {} {:?} ({:?})",
+        episode.id,
+        episode.title,
+        episode.duration
+    );
+    for segment in &episode.segments {
+        log::debug!(
+            "segment {} {:?} ({:?})",
+            segment.id,
+            segment.title,
+            segment.duration
+        );
+    }
+}
+
+async fn scrape_page(http_client: &HttpClient, url: &VideoUrl) -> Result<Analysis, Error> {
+    let Response {
+        body: html,
+        final_url,
+    } = http_client.get(url.as_ref().clone()).await?;
+    let scraping_error = |source| Error::ScrapingError {
+        url: final_url.clone(),
+        source,
+    };
+    let title = extract_title(&html).map_err(scraping_error)?;
+    check_youth_protection(extract_youth_protection(&html))?;
+
+    let segment_id = url.segment_id().as_deref();
+    let embedded = extract_episode(&html, url.video_id()).and_then(|episode| {
+        log_episode(&episode);
+        Ok((episode.videos(segment_id)?, episode.segment_videos()))
+    });
+    let (videos, segments) = match embedded {
+        Ok(videos) => videos,
+        Err(e) => {
+            log::debug!("{:#}, matching mpd-urls in the page instead", e);
+            if let Some(segment_id) = segment_id {
+                let mpd_url = extract_segment_url(&html, segment_id).map_err(scraping_error)?;
+                (vec![video_of(mpd_url)], vec![])
+            } else {
+                match extract_video_info(&html).map_err(scraping_error)? {
+                    Unsegmented(mpd_url) => (vec![video_of(mpd_url)], vec![]),
+                    Segmented(mpd_urls) => {
+                        let videos: Vec<Video> = mpd_urls.into_iter().map(video_of).collect();
+                        (videos.clone(), videos)
+                    }
+                }
+            }
+        }
+    };
+    if videos.iter().any(|v| is_austria_only(&v.mpd_url)) {
+        log::info!("{} is only offered for Austria", final_url);
+    }
+    Ok(Analysis {
+        title,
+        videos,
+        segments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Option<VideoIds> {
+        OrfOn.parse_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let ids = parse("https://on.orf.at/video/14225330").unwrap();
+        assert_eq!(ids.video_id, "14225330");
+        assert_eq!(ids.segment_id, None);
+
+        let ids =
+            parse("https://on.orf.at/video/14224991/willkommen-darmstadt-mit-stermann-grissemann")
+                .unwrap();
+        assert_eq!(ids.video_id, "14224991");
+        assert_eq!(ids.segment_id, None);
+
+        let ids =
+            parse("https://on.orf.at/video/14225651/15636092/gauder-fest-im-tiroler-zillertal")
+                .unwrap();
+        assert_eq!(ids.video_id, "14225651");
+        assert_eq!(ids.segment_id, Some("15636092".to_owned()));
+
+        assert!(parse("https://example.com/foo/a").is_none());
+        assert!(parse("https://on.orf.at/profile/8850/zib-1").is_none());
+    }
+
+    #[test]
+    fn test_parse_show_url() {
+        let parse = |url| OrfOn.parse_show_url(&Url::parse(url).unwrap());
+        assert_eq!(
+            parse("https://on.orf.at/profile/13886795/zib-1"),
+            Some("13886795".to_owned())
+        );
+        assert_eq!(
+            parse("https://on.orf.at/profile/13886795"),
+            Some("13886795".to_owned())
+        );
+        assert_eq!(parse("https://on.orf.at/video/14225330"), None);
+    }
+}
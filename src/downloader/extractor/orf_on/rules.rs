@@ -0,0 +1,221 @@
+//! Patterns for scraping the episode pages. They can be overridden in a rules file in the config
+//! directory, to work around changes of the markup without a new release.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::downloader::project_dirs;
+
+pub const RULES_FILE_NAME: &str = "orf_on_rules.json";
+
+const BASE_RE: &'static str = r"https?://[-a-zA-Z0-9.]+\.apa\.at/dash/cms-(austria|worldwide|worldwide_episodes)(/[-a-zA-Z0-9_]+)*";
+
+lazy_static! {
+    pub static ref RULES: Rules = load();
+}
+
+#[derive(Error, Debug)]
+pub enum RulesError {
+    #[error("could not read {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid rules file {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("invalid pattern {name}: {source}")]
+    Pattern {
+        name: &'static str,
+        #[source]
+        source: regex::Error,
+    },
+    #[error("pattern {name} lacks the capture group {group}")]
+    MissingGroup {
+        name: &'static str,
+        group: &'static str,
+    },
+}
+
+/// The patterns as written in the rules file, every one is optional. The mpd patterns are
+/// appended to `mpd_base`.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct Patterns {
+    title: String,
+    mpd_base: String,
+    unsegmented_mpd: String,
+    segment_mpd: String,
+    mpd: String,
+}
+
+impl Default for Patterns {
+    fn default() -> Self {
+        Self {
+            title: r#"<meta\s*property="og:title"\s*content="(.*)""#.to_owned(),
+            mpd_base: BASE_RE.to_owned(),
+            unsegmented_mpd: r"/[0-9]+_[0-9]+_QXB\.mp4/manifest\.mpd".to_owned(),
+            segment_mpd:
+                r"/[-a-zA-Z0-9_]+__s(?<segment_id>[0-9]+)_[-a-zA-Z0-9_]+_QXB\.mp4/manifest\.mpd"
+                    .to_owned(),
+            mpd: r"/[-a-zA-Z0-9_]+_QXB\.mp4/manifest\.mpd".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Rules {
+    /// Matches the title in its first capture group.
+    pub title: Regex,
+    /// Matches the mpd-url of a whole episode.
+    pub unsegmented_mpd: Regex,
+    /// Matches mpd-urls of segments, with their id in the group `segment_id`.
+    pub segment_mpd: Regex,
+    /// Matches all mpd-urls.
+    pub mpd: Regex,
+}
+
+fn compile(name: &'static str, pattern: &str) -> Result<Regex, RulesError> {
+    Regex::new(pattern).map_err(|source| RulesError::Pattern { name, source })
+}
+
+fn require_group(name: &'static str, re: &Regex, group: &'static str) -> Result<(), RulesError> {
+    let found = match group.parse::<usize>() {
+        Ok(index) => re.captures_len() > index,
+        Err(_) => re.capture_names().any(|n| n == Some(group)),
+    };
+    if found {
+        Ok(())
+    } else {
+        Err(RulesError::MissingGroup { name, group })
+    }
+}
+
+impl Rules {
+    fn compile(p: &Patterns) -> Result<Self, RulesError> {
+        let title = compile("title", &p.title)?;
+        require_group("title", &title, "1")?;
+        let segment_mpd = compile("segment_mpd", &format!("{}{}", p.mpd_base, p.segment_mpd))?;
+        require_group("segment_mpd", &segment_mpd, "segment_id")?;
+        Ok(Self {
+            title,
+            unsegmented_mpd: compile(
+                "unsegmented_mpd",
+                &format!("{}{}", p.mpd_base, p.unsegmented_mpd),
+            )?,
+            segment_mpd,
+            mpd: compile("mpd", &format!("{}{}", p.mpd_base, p.mpd))?,
+        })
+    }
+
+    /// Reads the rules from a file, using the built-in patterns for those it does not contain.
+    pub fn from_file(path: &Path) -> Result<Self, RulesError> {
+        let json = fs::read_to_string(path).map_err(|source| RulesError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+        let patterns = serde_json::from_str(&json).map_err(|source| RulesError::Parse {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::compile(&patterns)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::compile(&Patterns::default()).unwrap()
+    }
+}
+
+pub fn rules_path() -> Option<PathBuf> {
+    project_dirs().map(|d| d.config_dir().join(RULES_FILE_NAME))
+}
+
+fn load() -> Rules {
+    let Some(path) = rules_path().filter(|p| p.exists()) else {
+        return Rules::default();
+    };
+    match Rules::from_file(&path) {
+        Ok(rules) => {
+            log::info!("using extraction rules from {:?}", path);
+            rules
+        }
+        Err(e) => {
+            log::error!("{}, using the built-in extraction rules", e);
+            Rules::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_from_json(json: &str) -> Result<Rules, RulesError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(RULES_FILE_NAME);
+        fs::write(&path, json).unwrap();
+        Rules::from_file(&path)
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let rules = Rules::default();
+        assert!(rules
+            .title
+            .is_match(r#"<meta property="og:title" content="ZIB 1">"#));
+    }
+
+    #[test]
+    fn test_override_rules() {
+        let rules = rules_from_json(
+            r#"{"title": "<title>(.*)</title>", "mpd_base": "https://cdn\\.example\\.com"}"#,
+        )
+        .unwrap();
+        let captures = rules.title.captures("<title>ZIB 1</title>").unwrap();
+        assert_eq!(&captures[1], "ZIB 1");
+        assert!(rules
+            .mpd
+            .is_match("https://cdn.example.com/x_QXB.mp4/manifest.mpd"));
+        assert!(rules
+            .segment_mpd
+            .is_match("https://cdn.example.com/a__s15636092_b_QXB.mp4/manifest.mpd"));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(matches!(
+            rules_from_json(r#"{"title": "(unclosed"}"#),
+            Err(RulesError::Pattern { name: "title", .. })
+        ));
+        assert!(matches!(
+            rules_from_json(r#"{"title": "<title>.*</title>"}"#),
+            Err(RulesError::MissingGroup {
+                name: "title",
+                group: "1"
+            })
+        ));
+        assert!(matches!(
+            rules_from_json(r#"{"segment_mpd": "/s([0-9]+)\\.mpd"}"#),
+            Err(RulesError::MissingGroup {
+                name: "segment_mpd",
+                group: "segment_id"
+            })
+        ));
+        assert!(matches!(
+            rules_from_json(r#"{"titel": "<title>(.*)</title>"}"#),
+            Err(RulesError::Parse { .. })
+        ));
+    }
+}
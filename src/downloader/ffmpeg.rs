@@ -0,0 +1,252 @@
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Mutex;
+
+use thiserror::Error;
+use tokio::process::Command;
+
+const REQUIRED_MUXERS: [&str; 1] = ["mp4"];
+const REQUIRED_DEMUXERS: [&str; 2] = ["mov", "concat"];
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CheckError {
+    #[error("could not execute {0}")]
+    NotExecutable(String),
+    #[error("could not determine ffmpeg version")]
+    UnknownVersion,
+    #[error("missing formats: {}", .0.join(", "))]
+    MissingFormats(Vec<&'static str>),
+}
+
+const STDERR_TAIL_LINES: usize = 10;
+
+#[derive(Error, Debug)]
+pub enum RunError {
+    #[error("could not execute ffmpeg: {0}")]
+    Spawn(#[from] io::Error),
+    #[error("ffmpeg exited with {status}: {stderr_tail}")]
+    Failed {
+        status: ExitStatus,
+        /// Last lines of the error output.
+        stderr_tail: String,
+    },
+}
+
+impl RunError {
+    pub fn is_disk_full(&self) -> bool {
+        match self {
+            RunError::Spawn(_) => false,
+            RunError::Failed { stderr_tail, .. } => stderr_tail.contains("No space left on device"),
+        }
+    }
+}
+
+/// Returns the last `n` non-empty lines of `output`.
+fn tail(output: &str, n: usize) -> String {
+    let lines = output
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+pub struct Ffmpeg {
+    path: String,
+}
+
+/// Result of the last check, so ffmpeg isn't checked again until its path changes.
+#[derive(Default)]
+pub struct CheckCache {
+    last: Mutex<Option<(String, Result<String, CheckError>)>>,
+}
+
+impl CheckCache {
+    /// The result of the last check, if it was of the same ffmpeg.
+    pub fn get(&self, ffmpeg: &Ffmpeg) -> Option<Result<String, CheckError>> {
+        match &*self.last.lock().unwrap() {
+            Some((path, res)) if *path == ffmpeg.path => Some(res.clone()),
+            _ => None,
+        }
+    }
+
+    /// Checks ffmpeg and keeps the result.
+    pub async fn check(&self, ffmpeg: &Ffmpeg) -> Result<String, CheckError> {
+        let res = ffmpeg.check().await;
+        *self.last.lock().unwrap() = Some((ffmpeg.path.clone(), res.clone()));
+        res
+    }
+
+    /// Checks ffmpeg unless it passed the last check. Failures are checked again, in case ffmpeg
+    /// was installed since.
+    pub async fn ensure(&self, ffmpeg: &Ffmpeg) -> Result<String, CheckError> {
+        match self.get(ffmpeg) {
+            Some(Ok(version)) => Ok(version),
+            _ => self.check(ffmpeg).await,
+        }
+    }
+}
+
+impl Ffmpeg {
+    pub fn new(path: &str) -> Self {
+        let path = path.trim();
+        Self {
+            path: if path.is_empty() { "ffmpeg" } else { path }.to_owned(),
+        }
+    }
+
+    async fn output<I, S>(&self, args: I) -> Result<String, CheckError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let output = Command::new(&self.path)
+            .stdin(Stdio::null())
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| {
+                log::warn!("could not execute {}: {}", self.path, e);
+                CheckError::NotExecutable(self.path.clone())
+            })?;
+        if !output.status.success() {
+            return Err(CheckError::NotExecutable(self.path.clone()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Returns the version of ffmpeg, if it is executable and supports all formats oondl needs.
+    pub async fn check(&self) -> Result<String, CheckError> {
+        let version =
+            parse_version(&self.output(["-version"]).await?).ok_or(CheckError::UnknownVersion)?;
+
+        let muxers = parse_formats(&self.output(["-hide_banner", "-muxers"]).await?);
+        let demuxers = parse_formats(&self.output(["-hide_banner", "-demuxers"]).await?);
+        let missing = REQUIRED_MUXERS
+            .iter()
+            .filter(|m| !muxers.iter().any(|f| f == *m))
+            .chain(
+                REQUIRED_DEMUXERS
+                    .iter()
+                    .filter(|d| !demuxers.iter().any(|f| f == *d)),
+            )
+            .copied()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(CheckError::MissingFormats(missing));
+        }
+
+        log::debug!("using ffmpeg {} from {}", version, self.path);
+        Ok(version)
+    }
+
+    pub async fn run<I, S>(&self, args: I, opt_current_dir: Option<&Path>) -> Result<(), RunError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let args = args.into_iter().collect::<Vec<_>>();
+        log::debug!(
+            "running ffmpeg {:?}",
+            args.iter().map(|a| a.as_ref()).collect::<Vec<_>>()
+        );
+        let mut c = Command::new(&self.path);
+        c.stdin(Stdio::null());
+        c.kill_on_drop(true);
+        c.args(args);
+        if let Some(current_dir) = opt_current_dir {
+            c.current_dir(current_dir);
+        }
+
+        let output = c.output().await?;
+        log::debug!(
+            "stdout of ffmpeg: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        log::debug!(
+            "stderr of ffmpeg: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        if !output.status.success() {
+            return Err(RunError::Failed {
+                status: output.status,
+                stderr_tail: tail(&String::from_utf8_lossy(&output.stderr), STDERR_TAIL_LINES),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_version(output: &str) -> Option<String> {
+    output
+        .lines()
+        .next()?
+        .strip_prefix("ffmpeg version ")?
+        .split_whitespace()
+        .next()
+        .map(|v| v.to_owned())
+}
+
+/// Parses the output of `ffmpeg -muxers` or `ffmpeg -demuxers` into a list of format names.
+fn parse_formats(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|l| l.trim() != "--")
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().nth(1))
+        .flat_map(|names| names.split(','))
+        .map(|n| n.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let output =
+            "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers\n\
+                      built with gcc 13 (Ubuntu 13.2.0-23ubuntu3)\n";
+        assert_eq!(parse_version(output), Some("6.1.1-3ubuntu5".to_owned()));
+        assert_eq!(parse_version("foo version 1.0"), None);
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let output = "File formats:\n \
+                      D. = Demuxing supported\n \
+                      .E = Muxing supported\n \
+                      --\n \
+                      D  concat          Virtual concatenation script\n \
+                      D  mov,mp4,m4a,3gp,3g2,mj2 QuickTime / MOV\n";
+        assert_eq!(
+            parse_formats(output),
+            vec!["concat", "mov", "mp4", "m4a", "3gp", "3g2", "mj2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_cache() {
+        let cache = CheckCache::default();
+        let missing = Ffmpeg::new("/nonexistent/ffmpeg");
+        assert_eq!(cache.get(&missing), None);
+        let res = cache.ensure(&missing).await;
+        assert!(matches!(res, Err(CheckError::NotExecutable(_))));
+        assert_eq!(cache.get(&missing), Some(res));
+        assert_eq!(cache.get(&Ffmpeg::new("/other/ffmpeg")), None);
+
+        *cache.last.lock().unwrap() = Some((missing.path.clone(), Ok("6.1".to_owned())));
+        assert_eq!(cache.ensure(&missing).await, Ok("6.1".to_owned()));
+    }
+
+    #[test]
+    fn test_tail() {
+        let output = "line 1\n\nline 2\r\nline 3\n\n";
+        assert_eq!(tail(output, 2), "line 2\nline 3");
+        assert_eq!(tail(output, 5), "line 1\nline 2\nline 3");
+    }
+}
@@ -0,0 +1,287 @@
+//! Record of the finished downloads, kept in the data directory.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::{project_dirs, Quality};
+
+const HISTORY_FILE_NAME: &str = "history.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub video_id: String,
+    /// Part of the video, `None` if the whole episode was downloaded.
+    pub segment_id: Option<String>,
+    /// Segments picked from the episode, `None` if all of them were downloaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment_ids: Option<Vec<String>>,
+    pub title: String,
+    /// `None` in entries recorded before the quality was.
+    #[serde(default)]
+    pub quality: Option<Quality>,
+    /// Where the file was saved, `None` in entries recorded before the path was.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Size of the file in bytes, `None` in entries recorded before the size was.
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Time at which the download finished.
+    pub date: DateTime<Local>,
+}
+
+impl HistoryEntry {
+    /// Whether the title, the video id or the path contain `query`, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        self.title.to_lowercase().contains(&query)
+            || self.video_id.contains(&query)
+            || self
+                .path
+                .as_ref()
+                .is_some_and(|p| p.to_string_lossy().to_lowercase().contains(&query))
+    }
+}
+
+pub struct History {
+    /// File the entries are saved to, `None` to keep them in memory only.
+    path: Option<PathBuf>,
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Loads the history from `path`. A missing file yields an empty history, an unreadable one
+    /// is left untouched and the history is not saved.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let Some(path) = path else {
+            return Self::in_memory();
+        };
+        let entries = match fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str(&json) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::error!("invalid history file {:?}: {}", path, e);
+                    return Self::in_memory();
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => {
+                log::error!("could not read history file {:?}: {}", path, e);
+                return Self::in_memory();
+            }
+        };
+        Self {
+            path: Some(path),
+            entries,
+        }
+    }
+
+    fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: vec![],
+        }
+    }
+
+    /// Entries in the order they were added.
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Finds the latest download of the video, or of the given segment of it. Downloads of only
+    /// some of the segments don't count as download of the video.
+    pub fn find(&self, video_id: &str, segment_id: Option<&str>) -> Option<&HistoryEntry> {
+        self.find_selection(video_id, segment_id, None)
+    }
+
+    /// Finds the latest download covering the segments picked from the video, `None` standing
+    /// for all of them: a download of the whole video or of the same segments.
+    pub fn find_selection(
+        &self,
+        video_id: &str,
+        segment_id: Option<&str>,
+        segment_ids: Option<&[String]>,
+    ) -> Option<&HistoryEntry> {
+        let sorted = |ids: &[String]| {
+            let mut ids = ids.to_vec();
+            ids.sort();
+            ids
+        };
+        self.entries.iter().rev().find(|e| {
+            let covered = match (&e.segment_ids, segment_ids) {
+                (None, _) => true,
+                (Some(done), Some(wanted)) => sorted(done) == sorted(wanted),
+                (Some(_), None) => false,
+            };
+            e.video_id == video_id && e.segment_id.as_deref() == segment_id && covered
+        })
+    }
+
+    /// Whether the video, or the given segment of it, has been downloaded before.
+    pub fn contains(&self, video_id: &str, segment_id: Option<&str>) -> bool {
+        self.find(video_id, segment_id).is_some()
+    }
+
+    /// Adds an entry and saves the history.
+    pub fn add(&mut self, entry: HistoryEntry) -> io::Result<()> {
+        self.entries.push(entry);
+        self.save()
+    }
+
+    /// Removes an entry and saves the history, so the video is no longer reported as duplicate.
+    pub fn remove(&mut self, entry: &HistoryEntry) -> io::Result<()> {
+        self.entries.retain(|e| e != entry);
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.entries)?)?;
+        fs::rename(&temp_path, path)
+    }
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    project_dirs().map(|d| d.data_dir().join(HISTORY_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(video_id: &str, segment_id: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            video_id: video_id.to_owned(),
+            segment_id: segment_id.map(str::to_owned),
+            segment_ids: None,
+            title: "ZIB 1".to_owned(),
+            quality: Some(Quality::High),
+            path: Some(PathBuf::from("/videos/ZIB_1_14225651.mp4")),
+            size: Some(250_000_000),
+            date: Local::now(),
+        }
+    }
+
+    #[test]
+    fn test_add_and_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("data").join(HISTORY_FILE_NAME);
+
+        let mut history = History::load(Some(path.clone()));
+        assert!(history.entries().is_empty());
+        history.add(entry("14225651", None)).unwrap();
+        history.add(entry("14225651", Some("15636092"))).unwrap();
+
+        let history = History::load(Some(path));
+        assert_eq!(history.entries().len(), 2);
+        assert!(history.contains("14225651", None));
+        assert!(history.contains("14225651", Some("15636092")));
+        assert!(!history.contains("14225651", Some("15636091")));
+        assert!(!history.contains("14224545", None));
+    }
+
+    #[test]
+    fn test_selected_segments() {
+        let mut history = History::load(None);
+        let mut e = entry("14225651", None);
+        e.segment_ids = Some(vec!["15636091".to_owned()]);
+        history.add(e).unwrap();
+        assert!(!history.contains("14225651", None));
+
+        history.add(entry("14225651", None)).unwrap();
+        assert!(history.contains("14225651", None));
+    }
+
+    #[test]
+    fn test_find_selection() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let mut history = History::load(None);
+        let mut e = entry("14225651", None);
+        e.segment_ids = Some(ids(&["15636091", "15636093"]));
+        history.add(e).unwrap();
+
+        let same = ids(&["15636093", "15636091"]);
+        assert!(history
+            .find_selection("14225651", None, Some(&same))
+            .is_some());
+        let other = ids(&["15636092"]);
+        assert!(history
+            .find_selection("14225651", None, Some(&other))
+            .is_none());
+
+        history.add(entry("14225651", None)).unwrap();
+        assert!(history
+            .find_selection("14225651", None, Some(&other))
+            .is_some());
+    }
+
+    #[test]
+    fn test_remove() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(HISTORY_FILE_NAME);
+
+        let mut history = History::load(Some(path.clone()));
+        let e = entry("14225651", None);
+        history.add(e.clone()).unwrap();
+        history.add(entry("14224545", None)).unwrap();
+        history.remove(&e).unwrap();
+
+        let history = History::load(Some(path));
+        assert!(!history.contains("14225651", None));
+        assert!(history.contains("14224545", None));
+    }
+
+    #[test]
+    fn test_matches() {
+        let e = entry("14225651", None);
+        assert!(e.matches("zib"));
+        assert!(e.matches(" 14225651 "));
+        assert!(e.matches("/videos"));
+        assert!(!e.matches("wien heute"));
+    }
+
+    #[test]
+    fn test_load_old_format() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(HISTORY_FILE_NAME);
+        let json = r#"[
+  {
+    "video_id": "14225651",
+    "segment_id": null,
+    "title": "ZIB 1",
+    "date": "2024-04-24T19:50:00+02:00"
+  }
+]"#;
+        fs::write(&path, json).unwrap();
+
+        let mut history = History::load(Some(path.clone()));
+        let e = history.find("14225651", None).unwrap();
+        assert_eq!(e.quality, None);
+        assert_eq!(e.path, None);
+        assert!(e.matches("zib"));
+        history.add(entry("14224545", None)).unwrap();
+
+        let history = History::load(Some(path));
+        assert_eq!(history.entries().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_file_is_kept() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(HISTORY_FILE_NAME);
+        fs::write(&path, "[{").unwrap();
+
+        let mut history = History::load(Some(path.clone()));
+        history.add(entry("14225651", None)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[{");
+    }
+}
@@ -1,41 +1,124 @@
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::anyhow;
-use futures_util::StreamExt;
-use reqwest::{Client, ClientBuilder};
+use futures_util::{stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Certificate, Client, ClientBuilder, Proxy};
 use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+use tokio::{select, task};
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
-use super::Error;
+use super::chunk::{self, ChunkKind};
+use super::progress::ChunkEvent;
+use super::rate_limit::RateLimiter;
+use super::settings::{HttpSettingsError, ProxyMode};
+use super::{Error, Settings};
+
+fn client_builder(settings: &Settings) -> Result<ClientBuilder, HttpSettingsError> {
+    let mut builder = ClientBuilder::new()
+        .connect_timeout(Duration::from_secs(settings.connect_timeout.max(1)))
+        .read_timeout(Duration::from_secs(settings.read_timeout.max(1)));
+
+    match settings.proxy_mode {
+        // reqwest picks up the environment variables by default
+        ProxyMode::Environment => (),
+        ProxyMode::Disabled => builder = builder.no_proxy(),
+        ProxyMode::Custom => {
+            let url = settings.proxy_url.trim();
+            let proxy = Proxy::all(url).map_err(|_| HttpSettingsError::Proxy(url.to_owned()))?;
+            builder = builder.proxy(proxy);
+        }
+    }
+
+    for path in &settings.ca_certificates {
+        let certs = std::fs::read(path)
+            .ok()
+            .and_then(|pem| Certificate::from_pem_bundle(&pem).ok())
+            .filter(|certs| !certs.is_empty())
+            .ok_or_else(|| HttpSettingsError::Certificate(path.clone()))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    let user_agent = settings.user_agent.trim();
+    if !user_agent.is_empty() {
+        let value = HeaderValue::from_str(user_agent)
+            .map_err(|_| HttpSettingsError::UserAgent(user_agent.to_owned()))?;
+        builder = builder.user_agent(value);
+    }
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &settings.headers {
+        let name = name.trim();
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| HttpSettingsError::Header(name.to_owned()))?;
+        let header_value = HeaderValue::from_str(value.trim())
+            .map_err(|_| HttpSettingsError::Header(name.to_owned()))?;
+        headers.append(header_name, header_value);
+    }
+    Ok(builder.default_headers(headers))
+}
+
+/// Checks whether a client can be built with the network settings.
+pub fn check_settings(settings: &Settings) -> Result<(), HttpSettingsError> {
+    client_builder(settings).map(|_| ())
+}
+
+const CHUNK_ATTEMPTS: u32 = 3;
+const CHUNK_RETRY_DELAY: Duration = Duration::from_millis(500);
 
 pub struct Response {
     pub body: String,
     pub final_url: Url,
 }
 
+#[derive(Clone)]
 pub struct HttpClient {
     client: Client,
+    concurrent_chunks: usize,
+    /// Limits the number of chunks downloaded at the same time across all streams.
+    chunk_permits: Arc<Semaphore>,
+    rate_limiter: Arc<RateLimiter>,
 }
 
 impl HttpClient {
-    pub fn new() -> Self {
-        let client = ClientBuilder::new()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("could not build reqwest client");
-        Self { client }
+    pub fn new(settings: &Settings, rate_limiter: Arc<RateLimiter>) -> Result<Self, Error> {
+        let client = client_builder(settings)?.build()?;
+        let concurrent_chunks = settings.concurrent_chunks.max(1);
+        Ok(Self {
+            client,
+            concurrent_chunks,
+            chunk_permits: Arc::new(Semaphore::new(concurrent_chunks)),
+            rate_limiter,
+        })
     }
 
     async fn try_get(&self, url: Url) -> Result<reqwest::Response, Error> {
+        log::debug!("GET {}", url);
         let resp_result = self.client.get(url).send().await;
+        if let Ok(resp) = &resp_result {
+            log::debug!(
+                "{} {} {:?}",
+                resp.url(),
+                resp.status(),
+                resp.headers().get(CONTENT_TYPE)
+            );
+        }
         match resp_result {
-            Ok(resp) => match resp.error_for_status() {
-                Ok(ok_status_resp) => Ok(ok_status_resp),
-                Err(status_error) => Err(Error::UnexpectedError(anyhow!(status_error))),
-            },
+            Ok(resp) if resp.status().is_client_error() || resp.status().is_server_error() => {
+                Err(Error::HttpStatusError {
+                    url: resp.url().clone(),
+                    status: resp.status().as_u16(),
+                })
+            }
+            Ok(resp) => Ok(resp),
             Err(e) => Err(Error::NetworkError(e)),
         }
     }
@@ -54,18 +137,327 @@ impl HttpClient {
         &self,
         dest: &Path,
         chunk_urls: Vec<Url>,
-        on_chunk_downloaded: Arc<Mutex<impl FnMut()>>,
+        on_chunk_event: impl Fn(ChunkEvent) + Send + Sync + 'static,
     ) -> Result<(), Error> {
-        let mut file = File::create(dest).await?;
+        let file = File::create(dest).await?;
+        self.download_to(file, chunk_urls, on_chunk_event).await
+    }
+
+    async fn try_fetch_chunk(
+        &self,
+        url: &Url,
+        kind: ChunkKind,
+        on_chunk_event: &impl Fn(ChunkEvent),
+    ) -> Result<Vec<u8>, Error> {
+        let _permit = self.chunk_permits.acquire().await.unwrap();
+        let resp = self.try_get(url.clone()).await?;
+        let content_length = resp.content_length();
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        on_chunk_event(ChunkEvent::Started { content_length });
 
-        for url in chunk_urls {
-            let mut stream = self.try_get(url).await?.bytes_stream();
+        let mut chunk = Vec::with_capacity(content_length.unwrap_or(0) as usize);
+        let read_res: Result<(), Error> = async {
+            let mut stream = resp.bytes_stream();
             while let Some(item) = stream.next().await {
                 let bytes = item?;
-                file.write_all(&bytes).await?;
+                chunk.extend_from_slice(&bytes);
+                on_chunk_event(ChunkEvent::Received(bytes.len() as u64));
+                self.rate_limiter.acquire(bytes.len() as u64).await;
+            }
+            Ok(())
+        }
+        .await;
+        let res = read_res.and_then(|()| {
+            chunk::validate(kind, content_type.as_deref(), content_length, &chunk).map_err(
+                |reason| Error::InvalidChunkError {
+                    url: url.clone(),
+                    reason,
+                },
+            )
+        });
+
+        match res {
+            Ok(()) => {
+                on_chunk_event(ChunkEvent::Finished {
+                    content_length,
+                    size: chunk.len() as u64,
+                });
+                Ok(chunk)
+            }
+            Err(e) => {
+                on_chunk_event(ChunkEvent::Discarded {
+                    content_length,
+                    received: chunk.len() as u64,
+                });
+                Err(e)
             }
-            on_chunk_downloaded.lock().unwrap()();
         }
+    }
+
+    /// Fetches a chunk, retrying on network errors and invalid responses.
+    async fn fetch_chunk(
+        &self,
+        url: Url,
+        kind: ChunkKind,
+        on_chunk_event: &impl Fn(ChunkEvent),
+    ) -> Result<Vec<u8>, Error> {
+        let mut attempt = 1;
+        loop {
+            match self.try_fetch_chunk(&url, kind, on_chunk_event).await {
+                Err(e @ (Error::NetworkError(_) | Error::InvalidChunkError { .. }))
+                    if attempt < CHUNK_ATTEMPTS =>
+                {
+                    log::warn!("attempt {} to download chunk failed: {}", attempt, e);
+                    sleep(CHUNK_RETRY_DELAY * attempt).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Writes all chunks to `writer` in order and closes it afterwards. Several chunks are fetched
+    /// concurrently in separate tasks, so fetching goes on while writing is blocked, e.g. because
+    /// ffmpeg is waiting for another stream.
+    pub async fn download_to(
+        &self,
+        mut writer: impl AsyncWrite + Unpin,
+        chunk_urls: Vec<Url>,
+        on_chunk_event: impl Fn(ChunkEvent) + Send + Sync + 'static,
+    ) -> Result<(), Error> {
+        let on_chunk_event = Arc::new(on_chunk_event);
+        let cancel_token = CancellationToken::new();
+        let _cancel_guard = cancel_token.clone().drop_guard();
+
+        // the first chunk of a stream is the initialization segment
+        let mut chunks = stream::iter(chunk_urls.into_iter().enumerate())
+            .map(|(idx, url)| {
+                let kind = if idx == 0 {
+                    ChunkKind::Init
+                } else {
+                    ChunkKind::Media
+                };
+                let client = self.clone();
+                let on_chunk_event = on_chunk_event.clone();
+                let cancel_token = cancel_token.clone();
+                task::spawn(async move {
+                    select! {
+                        res = client.fetch_chunk(url, kind, &*on_chunk_event) => Some(res),
+                        _ = cancel_token.cancelled() => None,
+                    }
+                })
+            })
+            .buffered(self.concurrent_chunks);
+
+        while let Some(join_res) = chunks.next().await {
+            let chunk = join_res
+                .map_err(|e| Error::UnexpectedError(anyhow!(e)))?
+                .expect("chunk download cancelled")?;
+            writer.write_all(&chunk).await?;
+        }
+        writer.shutdown().await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use super::super::test_server::{init_segment, media_segment, mp4_box, Route, TestServer};
+    use super::super::ChunkError;
+    use super::*;
+
+    fn test_client(settings: &Settings) -> HttpClient {
+        HttpClient::new(settings, Arc::new(RateLimiter::new(settings))).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_download_to_keeps_order() {
+        let paths = (0..8).map(|i| format!("/{}.m4s", i)).collect::<Vec<_>>();
+        let bodies = (0..8)
+            .map(|i| {
+                if i == 0 {
+                    init_segment()
+                } else {
+                    media_segment(format!("{};", i).as_bytes())
+                }
+            })
+            .collect::<Vec<_>>();
+        // later chunks are answered faster, so they arrive out of order
+        let server = TestServer::start(
+            paths
+                .iter()
+                .zip(&bodies)
+                .enumerate()
+                .map(|(i, (p, body))| {
+                    let route = Route::ok("video/mp4", body.clone())
+                        .delayed(Duration::from_millis(80 - i as u64 * 10));
+                    (p.as_str(), route)
+                })
+                .collect(),
+        )
+        .await;
+        let client = test_client(&Settings::default());
+
+        let finished = Arc::new(Mutex::new(0));
+        let finished_clone = finished.clone();
+        let mut out = vec![];
+        client
+            .download_to(
+                &mut out,
+                paths.iter().map(|p| server.url(p)).collect(),
+                move |e| {
+                    if let ChunkEvent::Finished { .. } = e {
+                        *finished_clone.lock().unwrap() += 1;
+                    }
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(out, bodies.concat());
+        assert_eq!(*finished.lock().unwrap(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_download_to_fails_on_error_status() {
+        let server =
+            TestServer::start(vec![("/0.m4s", Route::ok("video/mp4", init_segment()))]).await;
+        let client = test_client(&Settings::default());
+
+        let mut out = vec![];
+        let res = client
+            .download_to(
+                &mut out,
+                vec![server.url("/0.m4s"), server.url("/missing.m4s")],
+                |_| (),
+            )
+            .await;
+        match res {
+            Err(Error::HttpStatusError { url, status }) => {
+                assert_eq!(url, server.url("/missing.m4s"));
+                assert_eq!(status, 404);
+            }
+            _ => panic!("expected HTTP status error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_is_rate_limited() {
+        let server = TestServer::start(vec![
+            (
+                "/0.m4s",
+                Route::ok(
+                    "video/mp4",
+                    [mp4_box("ftyp", &[]), mp4_box("moov", &[0; 1_984])].concat(),
+                ),
+            ),
+            ("/1.m4s", Route::ok("video/mp4", media_segment(&[0; 1_984]))),
+        ])
+        .await;
+        let client = test_client(&Settings {
+            rate_limit: Some(10_000),
+            ..Settings::default()
+        });
+
+        let start = std::time::Instant::now();
+        let mut out = vec![];
+        client
+            .download_to(
+                &mut out,
+                vec![server.url("/0.m4s"), server.url("/1.m4s")],
+                |_| (),
+            )
+            .await
+            .unwrap();
+        assert_eq!(out.len(), 4_000);
+        assert!(start.elapsed() >= Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn test_download_to_rejects_invalid_chunks() {
+        let server = TestServer::start(vec![
+            ("/0.m4s", Route::ok("video/mp4", init_segment())),
+            ("/1.m4s", Route::ok("text/html", "<html>Login</html>")),
+        ])
+        .await;
+        let client = test_client(&Settings::default());
+
+        let mut out = vec![];
+        let res = client
+            .download_to(
+                &mut out,
+                vec![server.url("/0.m4s"), server.url("/1.m4s")],
+                |_| (),
+            )
+            .await;
+        match res {
+            Err(Error::InvalidChunkError { url, reason }) => {
+                assert_eq!(url, server.url("/1.m4s"));
+                assert_eq!(reason, ChunkError::ContentType("text/html".to_owned()));
+            }
+            _ => panic!("expected invalid chunk error"),
+        }
+        let requests = server.requests();
+        let attempts = requests.iter().filter(|r| r.starts_with("GET /1.m4s"));
+        assert_eq!(attempts.count(), CHUNK_ATTEMPTS as usize);
+    }
+
+    #[tokio::test]
+    async fn test_user_agent_and_headers_are_sent() {
+        let server = TestServer::start(vec![("/", Route::ok("text/html", "ok"))]).await;
+        let client = test_client(&Settings {
+            user_agent: "oondl-test".to_owned(),
+            headers: vec![("X-Test".to_owned(), " 1 ".to_owned())],
+            ..Settings::default()
+        });
+
+        client.get(server.url("/")).await.unwrap();
+        let request = server.requests().pop().unwrap().to_lowercase();
+        assert!(request.contains("user-agent: oondl-test\r\n"));
+        assert!(request.contains("x-test: 1\r\n"));
+    }
+
+    #[test]
+    fn test_check_settings() {
+        assert_eq!(check_settings(&Settings::default()), Ok(()));
+        assert_eq!(
+            check_settings(&Settings {
+                proxy_mode: ProxyMode::Custom,
+                proxy_url: "socks5://localhost:1080".to_owned(),
+                ..Settings::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            check_settings(&Settings {
+                proxy_mode: ProxyMode::Custom,
+                proxy_url: "localhost:1080 x".to_owned(),
+                ..Settings::default()
+            }),
+            Err(HttpSettingsError::Proxy("localhost:1080 x".to_owned()))
+        );
+        assert_eq!(
+            check_settings(&Settings {
+                ca_certificates: vec![PathBuf::from("does/not/exist.pem")],
+                ..Settings::default()
+            }),
+            Err(HttpSettingsError::Certificate(PathBuf::from(
+                "does/not/exist.pem"
+            )))
+        );
+        assert_eq!(
+            check_settings(&Settings {
+                headers: vec![("X Test".to_owned(), "1".to_owned())],
+                ..Settings::default()
+            }),
+            Err(HttpSettingsError::Header("X Test".to_owned()))
+        );
+    }
+}
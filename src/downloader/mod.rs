@@ -1,34 +1,145 @@
+mod chunk;
 mod client;
 mod download;
+mod extractor;
+mod ffmpeg;
+mod history;
 mod http;
 mod models;
+mod mp4;
+mod progress;
+mod rate_limit;
+mod settings;
+mod subscription;
+#[cfg(test)]
+mod test_server;
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
+use chrono::{DateTime, Local};
 use models::OnErrorAction;
 use thiserror::Error;
 use tokio::sync::mpsc::{channel, unbounded_channel, UnboundedSender};
 use tokio::sync::Notify;
 use tokio::{runtime, select, task};
 use tokio_util::sync::CancellationToken;
+use url::Url;
 
+pub use self::chunk::ChunkError;
 pub use self::client::Client;
-use self::download::download;
+pub use self::download::VerifyError;
+use self::download::{download, estimate};
+use self::ffmpeg::{CheckCache, Ffmpeg};
+pub use self::ffmpeg::{CheckError as FfmpegCheckError, RunError as FfmpegRunError};
+pub use self::history::HistoryEntry;
+use self::history::{history_path, History};
+pub use self::http::check_settings as check_http_settings;
 use self::http::HttpClient;
-pub use self::models::{DownloadRequest, OonUrl, Phase, Quality, State, StateUpdate};
+use self::models::ListRequest;
+pub use self::models::{
+    DownloadRequest, EpisodeSegments, Phase, Quality, SegmentInfo, SegmentOutput, ShowEpisode,
+    ShowUrl, State, StateUpdate, TimeWindow, VerifyWarning, VideoUrl,
+};
+pub use self::mp4::TrackKind;
+pub use self::progress::Progress;
+use self::rate_limit::RateLimiter;
+pub use self::settings::{project_dirs, HttpSettingsError, ProxyMode, RateLimitRule, Settings};
+pub use self::subscription::{Region, Subscription};
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("network error: {0}")]
     NetworkError(#[from] reqwest::Error),
+    #[error("{url} returned HTTP status {status}")]
+    HttpStatusError { url: Url, status: u16 },
+    #[error("{url} is only available in Austria")]
+    GeoBlockedError { url: Url },
+    #[error("only available between {} and {} because of youth protection", .window.start.format("%H:%M"), .window.end.format("%H:%M"))]
+    YouthProtectionError { window: TimeWindow },
+    #[error("invalid network settings: {0}")]
+    HttpSettingsError(#[from] HttpSettingsError),
+    #[error("could not extract video information from {url}")]
+    ScrapingError {
+        url: Url,
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error("could not parse MPD {url}")]
+    MpdError {
+        url: Url,
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error("invalid chunk {url}: {reason}")]
+    InvalidChunkError { url: Url, reason: ChunkError },
+    #[error("verification of the downloaded file failed: {0}")]
+    VerificationError(#[from] VerifyError),
     #[error("error writing to file: {0}")]
-    FileError(#[from] std::io::Error),
+    FileError(std::io::Error),
+    #[error("disk full: {0}")]
+    DiskFullError(std::io::Error),
+    #[error("not enough space in {dir:?}: {required} bytes required, {available} available")]
+    DiskSpaceError {
+        dir: PathBuf,
+        required: u64,
+        available: u64,
+    },
+    #[error("ffmpeg not available: {0}")]
+    FfmpegMissingError(#[from] FfmpegCheckError),
+    #[error(transparent)]
+    FfmpegError(#[from] FfmpegRunError),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
 
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::StorageFull {
+            Error::DiskFullError(e)
+        } else {
+            Error::FileError(e)
+        }
+    }
+}
+
+/// Removes the first request from the queue that may be started at `now`.
+fn pop_ready(
+    queue: &mut VecDeque<DownloadRequest>,
+    now: DateTime<Local>,
+) -> Option<DownloadRequest> {
+    let index = queue.iter().position(|r| r.is_ready(now))?;
+    queue.remove(index)
+}
+
+/// Time at which the next deferred request becomes ready.
+fn next_wakeup(queue: &VecDeque<DownloadRequest>) -> Option<DateTime<Local>> {
+    queue.iter().filter_map(|r| r.not_before).min()
+}
+
+/// Analyzes a video to offer its segments for selection.
+async fn list_segments(http_client: &HttpClient, url: VideoUrl) -> Result<EpisodeSegments, Error> {
+    let analysis = url.extractor().analyze(http_client, &url).await?;
+    Ok(EpisodeSegments {
+        url,
+        title: analysis.title,
+        segments: analysis
+            .segments
+            .into_iter()
+            .filter_map(|v| {
+                Some(SegmentInfo {
+                    id: v.segment_id?,
+                    title: v.title,
+                    duration: v.duration,
+                })
+            })
+            .collect(),
+    })
+}
+
+#[derive(Clone)]
 struct ClientRef {
     ctx: egui::Context,
     sender: UnboundedSender<StateUpdate>,
@@ -45,10 +156,23 @@ impl ClientRef {
     }
 }
 
-pub fn run(ctx: egui::Context) -> Client {
+pub fn run(ctx: egui::Context, settings: Settings) -> Client {
+    extractor::load_rules();
     let shutdown_token = CancellationToken::new();
     let cloned_shutdown_token = shutdown_token.clone();
 
+    let rate_limiter = Arc::new(RateLimiter::new(&settings));
+    let rate_limiter_clone = rate_limiter.clone();
+    let settings = Arc::new(Mutex::new(settings));
+    let settings_clone = settings.clone();
+    let settings_notifier = Arc::new(Notify::new());
+    let settings_notifier_clone = settings_notifier.clone();
+    let subscriptions_notifier = Arc::new(Notify::new());
+    let subscriptions_notifier_clone = subscriptions_notifier.clone();
+    let history = Arc::new(Mutex::new(History::load(history_path())));
+    let history_clone = history.clone();
+    let ffmpeg_check = Arc::new(CheckCache::default());
+
     let request_queue: Arc<Mutex<VecDeque<DownloadRequest>>> =
         Arc::new(Mutex::new(VecDeque::new()));
     let request_queue_clone = request_queue.clone();
@@ -56,7 +180,16 @@ pub fn run(ctx: egui::Context) -> Client {
     let worker_notifier_clone = worker_notifier.clone();
 
     let (state_update_sender, state_update_receiver) = unbounded_channel::<StateUpdate>();
-    let client_ref = ClientRef::new(state_update_sender, ctx);
+    let client_ref = Arc::new(ClientRef::new(state_update_sender, ctx));
+    let checker_client_ref = client_ref.clone();
+
+    let (estimate_sender, mut estimate_receiver) = unbounded_channel::<DownloadRequest>();
+    let estimator_client_ref = client_ref.clone();
+    let subscriber_estimate_sender = estimate_sender.clone();
+    let subscriber_client_ref = client_ref.clone();
+
+    let (list_sender, mut list_receiver) = unbounded_channel::<ListRequest>();
+    let lister_client_ref = client_ref.clone();
 
     let (cancel_download_sender, mut cancel_download_receiver) = channel::<()>(1);
     let (on_error_sender, mut on_error_receiver) = channel::<OnErrorAction>(1);
@@ -70,21 +203,179 @@ pub fn run(ctx: egui::Context) -> Client {
             .unwrap();
 
         rt.block_on(async {
+            let checker_settings = settings.clone();
+            let checker_cache = ffmpeg_check.clone();
+            let ffmpeg_checker = task::spawn(async move {
+                loop {
+                    let path = checker_settings.lock().unwrap().ffmpeg_path.clone();
+                    let ffmpeg = Ffmpeg::new(&path);
+                    // only checked again if the path changed
+                    let res = match checker_cache.get(&ffmpeg) {
+                        Some(res) => res,
+                        None => checker_cache.check(&ffmpeg).await,
+                    };
+                    if let Err(e) = &res {
+                        log::warn!("ffmpeg check failed: {}", e);
+                    }
+                    checker_client_ref.send(StateUpdate::FfmpegChecked(res));
+                    settings_notifier.notified().await;
+                }
+            });
+
+            let estimator_settings = settings.clone();
+            let estimator_rate_limiter = rate_limiter.clone();
+            let estimator = task::spawn(async move {
+                while let Some(request) = estimate_receiver.recv().await {
+                    let http_client = HttpClient::new(
+                        &estimator_settings.lock().unwrap(),
+                        estimator_rate_limiter.clone(),
+                    );
+                    let res = match http_client {
+                        Ok(http_client) => estimate(&http_client, &request).await,
+                        Err(e) => Err(e),
+                    };
+                    match res {
+                        Ok((title, size)) => estimator_client_ref.send(StateUpdate::Estimated {
+                            request_id: request.id(),
+                            title,
+                            size,
+                        }),
+                        Err(e) => log::warn!("could not estimate size of {}: {}", request.url.as_str(), e),
+                    }
+                }
+            });
+
+            let lister_settings = settings.clone();
+            let lister_rate_limiter = rate_limiter.clone();
+            let lister = task::spawn(async move {
+                while let Some(request) = list_receiver.recv().await {
+                    let http_client = HttpClient::new(
+                        &lister_settings.lock().unwrap(),
+                        lister_rate_limiter.clone(),
+                    );
+                    let http_client = match http_client {
+                        Ok(http_client) => http_client,
+                        Err(e) => {
+                            lister_client_ref.send(match request {
+                                ListRequest::Show(_) => StateUpdate::ShowListed(Err(e)),
+                                ListRequest::Segments(_) => StateUpdate::SegmentsListed(Err(e)),
+                            });
+                            continue;
+                        }
+                    };
+                    match request {
+                        ListRequest::Show(url) => {
+                            let res = url.extractor().list_episodes(&http_client, &url).await;
+                            if let Err(e) = &res {
+                                log::warn!("could not list episodes of {}: {}", url.as_str(), e);
+                            }
+                            lister_client_ref.send(StateUpdate::ShowListed(res));
+                        }
+                        ListRequest::Segments(url) => {
+                            let res = list_segments(&http_client, url).await;
+                            if let Err(e) = &res {
+                                log::warn!("could not list segments: {}", e);
+                            }
+                            lister_client_ref.send(StateUpdate::SegmentsListed(res));
+                        }
+                    }
+                }
+            });
+
+            let subscriber_settings = settings.clone();
+            let subscriber_rate_limiter = rate_limiter.clone();
+            let subscriber_history = history.clone();
+            let subscriber_queue = request_queue.clone();
+            let subscriber_worker_notifier = worker_notifier.clone();
+            let subscriber = task::spawn(async move {
+                // Episodes enqueued in this session, which are not in the history until they
+                // are finished.
+                let mut enqueued = HashSet::new();
+                loop {
+                    let s = subscriber_settings.lock().unwrap().clone();
+                    for subscription in s.subscriptions.iter().filter(|s| s.enabled) {
+                        let res = match HttpClient::new(&s, subscriber_rate_limiter.clone()) {
+                            Ok(http_client) => {
+                                subscription::check(&http_client, subscription, |e| {
+                                    enqueued.contains(e.url.video_id())
+                                        || subscriber_history
+                                            .lock()
+                                            .unwrap()
+                                            .contains(e.url.video_id(), None)
+                                })
+                                .await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        let episodes = match res {
+                            Ok(episodes) => episodes,
+                            Err(e) => {
+                                log::warn!("could not check subscription {}: {}", subscription.url, e);
+                                continue;
+                            }
+                        };
+                        for episode in episodes {
+                            log::info!("enqueueing new episode {}", episode.url.as_str());
+                            enqueued.insert(episode.url.video_id().to_owned());
+                            let request = DownloadRequest::new(
+                                episode.url,
+                                subscription.quality,
+                                subscription.dest_dir.clone(),
+                            );
+                            subscriber_client_ref.send(StateUpdate::Enqueued {
+                                request_id: request.id(),
+                                title: episode.title,
+                            });
+                            if let Err(e) = subscriber_estimate_sender.send(request.clone()) {
+                                log::error!("could not send request for estimation: {}", e);
+                            }
+                            subscriber_queue.lock().unwrap().push_back(request);
+                            subscriber_worker_notifier.notify_one();
+                        }
+                    }
+                    let interval = std::time::Duration::from_secs(s.subscription_interval.max(1) * 60);
+                    select! {
+                        _ = tokio::time::sleep(interval) => {},
+                        _ = subscriptions_notifier.notified() => {},
+                    }
+                }
+            });
+
             let worker = task::spawn(async move {
-                let http_client = HttpClient::new();
                 loop {
-                    let r = request_queue.lock().unwrap().pop_front();
-                    if let Some(request) = r {
+                    let r = pop_ready(&mut request_queue.lock().unwrap(), Local::now());
+                    if let Some(mut request) = r {
                         select! {
                             _ = async {
                                 loop {
-                                    match download(&http_client, &client_ref, request.clone()).await {
-                                        Ok(()) => break,
+                                    let s = settings.lock().unwrap().clone();
+                                    let res = match HttpClient::new(&s, rate_limiter.clone()) {
+                                        Ok(http_client) => download(&http_client, &client_ref, &s, &ffmpeg_check, request.clone()).await,
+                                        Err(e) => Err(e),
+                                    };
+                                    match res {
+                                        Ok(entry) => {
+                                            if let Err(e) = history.lock().unwrap().add(entry) {
+                                                log::error!("could not save the history: {}", e);
+                                            }
+                                            break;
+                                        }
                                         Err(e) => {
                                             log::error!("error while downloading: {}", e);
                                             client_ref.send(StateUpdate::Error(e));
                                             match on_error_receiver.recv().await.unwrap() {
                                                 OnErrorAction::Retry => (),
+                                                OnErrorAction::Defer(not_before) => {
+                                                    log::info!("deferring download until {}", not_before);
+                                                    request.not_before = Some(not_before);
+                                                    client_ref.send(StateUpdate::Deferred {
+                                                        request_id: request.id(),
+                                                        url: request.url.as_ref().clone(),
+                                                        not_before,
+                                                    });
+                                                    request_queue.lock().unwrap().push_back(request.clone());
+                                                    break;
+                                                }
                                                 OnErrorAction::Cancel => break,
                                             }
                                         },
@@ -97,13 +388,27 @@ pub fn run(ctx: egui::Context) -> Client {
                         }
                     } else {
                         client_ref.send(StateUpdate::Idle);
-                        worker_notifier.notified().await;
+                        let wakeup = next_wakeup(&request_queue.lock().unwrap());
+                        match wakeup {
+                            Some(wakeup) => {
+                                let delay = (wakeup - Local::now()).to_std().unwrap_or_default();
+                                select! {
+                                    _ = tokio::time::sleep(delay) => {},
+                                    _ = worker_notifier.notified() => {},
+                                }
+                            }
+                            None => worker_notifier.notified().await,
+                        }
                     }
                 }
             });
 
             select! {
                 _ = worker => {},
+                _ = ffmpeg_checker => {},
+                _ = estimator => {},
+                _ = lister => {},
+                _ = subscriber => {},
                 _ = shutdown_token.cancelled() => {}
             }
         });
@@ -117,6 +422,46 @@ pub fn run(ctx: egui::Context) -> Client {
         thread_handle,
         request_queue_clone,
         worker_notifier_clone,
+        estimate_sender,
+        list_sender,
+        settings_clone,
+        settings_notifier_clone,
+        subscriptions_notifier_clone,
+        rate_limiter_clone,
+        history_clone,
         state_update_receiver,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_pop_ready() {
+        let now = Local::now();
+        let request = |not_before| {
+            let mut r = DownloadRequest::new(
+                VideoUrl::new("https://on.orf.at/video/14225330").unwrap(),
+                Quality::High,
+                PathBuf::new(),
+            );
+            r.not_before = not_before;
+            r
+        };
+        let deferred = request(Some(now + Duration::hours(2)));
+        let ready = request(None);
+        let mut queue = VecDeque::from([deferred.clone(), ready.clone()]);
+
+        assert_eq!(next_wakeup(&queue), deferred.not_before);
+        assert_eq!(pop_ready(&mut queue, now).map(|r| r.id()), Some(ready.id()));
+        assert!(pop_ready(&mut queue, now).is_none());
+        assert_eq!(
+            pop_ready(&mut queue, now + Duration::hours(3)).map(|r| r.id()),
+            Some(deferred.id())
+        );
+        assert_eq!(next_wakeup(&queue), None);
+    }
+}
@@ -1,13 +1,14 @@
-use std::fmt::Display;
+use std::fmt::{self, Display};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 
-use lazy_static::lazy_static;
-use regex::Regex;
+use chrono::{DateTime, Days, Local, NaiveTime, TimeZone};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use super::Error;
+use super::extractor::{self, Extractor};
+use super::{Error, FfmpegCheckError, Progress, VerifyError};
 
 static NEXT_ID: AtomicU32 = AtomicU32::new(0);
 
@@ -20,40 +21,35 @@ impl Display for ValidationError {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct OonUrl {
+/// Url of a video on one of the supported sites.
+#[derive(Clone)]
+pub struct VideoUrl {
     url: Url,
+    extractor: &'static dyn Extractor,
     video_id: String,
     segment_id: Option<String>,
 }
 
-impl OonUrl {
+impl VideoUrl {
     pub fn new(url_str: &str) -> Result<Self, ValidationError> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(
-                r"^https?://on\.orf\.at/video/(?<video_id>[0-9]+)(/(?<segment_id>[0-9]+))?(/.+)?$"
-            )
-            .unwrap();
-        }
-        if let Some(cap) = RE.captures(url_str) {
-            let url = Url::parse(url_str).unwrap();
-            let video_id = cap.name("video_id").unwrap().as_str().to_owned();
-            let segment_id = cap.name("segment_id").map(|s| s.as_str().to_owned());
-
-            Ok(Self {
-                url,
-                video_id,
-                segment_id,
-            })
-        } else {
-            Err(ValidationError)
-        }
+        let url = Url::parse(url_str).map_err(|_| ValidationError)?;
+        let (extractor, ids) = extractor::find(&url).ok_or(ValidationError)?;
+        Ok(Self {
+            url,
+            extractor,
+            video_id: ids.video_id,
+            segment_id: ids.segment_id,
+        })
     }
 
     pub fn as_str(&self) -> &str {
         self.url.as_str()
     }
 
+    pub(super) fn extractor(&self) -> &'static dyn Extractor {
+        self.extractor
+    }
+
     pub fn video_id(&self) -> &str {
         &self.video_id
     }
@@ -63,44 +59,191 @@ impl OonUrl {
     }
 }
 
-impl AsRef<Url> for OonUrl {
+impl fmt::Debug for VideoUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VideoUrl")
+            .field("url", &self.url.as_str())
+            .field("extractor", &self.extractor.name())
+            .field("video_id", &self.video_id)
+            .field("segment_id", &self.segment_id)
+            .finish()
+    }
+}
+
+impl AsRef<Url> for VideoUrl {
+    fn as_ref(&self) -> &Url {
+        &self.url
+    }
+}
+
+/// Url of a show on one of the supported sites, whose episodes can be listed.
+#[derive(Clone)]
+pub struct ShowUrl {
+    url: Url,
+    extractor: &'static dyn Extractor,
+    show_id: String,
+}
+
+impl ShowUrl {
+    pub fn new(url_str: &str) -> Result<Self, ValidationError> {
+        let url = Url::parse(url_str).map_err(|_| ValidationError)?;
+        let (extractor, show_id) = extractor::find_show(&url).ok_or(ValidationError)?;
+        Ok(Self {
+            url,
+            extractor,
+            show_id,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.url.as_str()
+    }
+
+    pub(super) fn extractor(&self) -> &'static dyn Extractor {
+        self.extractor
+    }
+
+    pub fn show_id(&self) -> &str {
+        &self.show_id
+    }
+}
+
+impl fmt::Debug for ShowUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShowUrl")
+            .field("url", &self.url.as_str())
+            .field("extractor", &self.extractor.name())
+            .field("show_id", &self.show_id)
+            .finish()
+    }
+}
+
+impl AsRef<Url> for ShowUrl {
     fn as_ref(&self) -> &Url {
         &self.url
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Currently available episode of a show.
+#[derive(Clone, Debug)]
+pub struct ShowEpisode {
+    pub url: VideoUrl,
+    pub title: String,
+    /// Time of the broadcast.
+    pub date: Option<DateTime<Local>>,
+    pub duration: Option<Duration>,
+}
+
+/// How the segments of a segmented episode are saved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SegmentOutput {
+    /// Concatenated into one file.
+    #[default]
+    Combined,
+    /// As numbered files in a folder named after the episode.
+    Separate,
+    /// Both as one file and as separate files.
+    Both,
+}
+
+/// Segment of an episode, to pick those to download.
+#[derive(Clone, Debug)]
+pub struct SegmentInfo {
+    pub id: String,
+    pub title: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+/// The segments of an episode, empty if it is not segmented.
+#[derive(Clone, Debug)]
+pub struct EpisodeSegments {
+    pub url: VideoUrl,
+    pub title: String,
+    pub segments: Vec<SegmentInfo>,
+}
+
+/// Lookup the downloader does in the background for the GUI.
+pub(super) enum ListRequest {
+    Show(ShowUrl),
+    Segments(VideoUrl),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Quality {
     Low,
     Medium,
     High,
 }
 
+/// Daily period of time. Windows with `end` before `start` span midnight.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    /// The next time the window opens after `now`.
+    pub fn next_start(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let mut start = now.date_naive().and_time(self.start);
+        if start <= now.naive_local() {
+            start = start + Days::new(1);
+        }
+        // the start time may not exist on the day of a DST change
+        Local
+            .from_local_datetime(&start)
+            .earliest()
+            .unwrap_or_else(|| now + chrono::Duration::hours(1))
+    }
+}
+
 #[derive(Clone)]
 pub struct DownloadRequest {
     id: u32,
-    pub url: OonUrl,
+    pub url: VideoUrl,
     pub quality: Quality,
     pub dest_dir: PathBuf,
+    /// Ids of the segments to download and concatenate, `None` for the whole video.
+    pub segment_ids: Option<Vec<String>>,
+    pub segment_output: SegmentOutput,
+    /// The request is not started before this time.
+    pub(super) not_before: Option<DateTime<Local>>,
 }
 
 impl DownloadRequest {
-    pub fn new(url: OonUrl, quality: Quality, dest_dir: PathBuf) -> Self {
+    pub fn new(url: VideoUrl, quality: Quality, dest_dir: PathBuf) -> Self {
         Self {
             id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             url,
             quality,
             dest_dir,
+            segment_ids: None,
+            segment_output: SegmentOutput::Combined,
+            not_before: None,
         }
     }
 
     pub fn id(&self) -> u32 {
         self.id
     }
+
+    pub(super) fn is_ready(&self, now: DateTime<Local>) -> bool {
+        self.not_before.is_none_or(|t| t <= now)
+    }
 }
 
 pub(super) enum OnErrorAction {
     Retry,
+    /// Keep the request queued and start it again at the given time.
+    Defer(DateTime<Local>),
     Cancel,
 }
 
@@ -108,45 +251,101 @@ pub(super) enum OnErrorAction {
 pub enum Phase {
     Idle,
     Analyzing,
-    Downloading { video_no: (u16, u16), progress: f32 },
+    Downloading {
+        video_no: (u16, u16),
+        progress: Progress,
+    },
     Merging,
 }
 
 pub enum StateUpdate {
-    StartedRequest { request_id: u32 },
+    StartedRequest {
+        request_id: u32,
+        url: Url,
+    },
     Title(String),
-    StartedVideo { video_no: u16, total_videos: u16 },
-    Downloaded(f32),
+    StartedVideo {
+        video_no: u16,
+        total_videos: u16,
+    },
+    Estimated {
+        request_id: u32,
+        title: String,
+        size: u64,
+    },
+    Downloaded(Progress),
     Merging,
+    /// A request was added to the queue by the downloader, for a subscription.
+    Enqueued {
+        request_id: u32,
+        title: String,
+    },
+    Deferred {
+        request_id: u32,
+        url: Url,
+        not_before: DateTime<Local>,
+    },
     Idle,
     Error(Error),
+    /// The download was saved, but did not pass the verification.
+    VerificationFailed(VerifyWarning),
+    FfmpegChecked(Result<String, FfmpegCheckError>),
+    ShowListed(Result<Vec<ShowEpisode>, Error>),
+    SegmentsListed(Result<EpisodeSegments, Error>),
+}
+
+/// A saved download that may be incomplete.
+#[derive(Debug)]
+pub struct VerifyWarning {
+    pub title: String,
+    pub path: PathBuf,
+    pub error: VerifyError,
 }
 
 pub struct QueueItem {
     pub request_id: u32,
     pub title: String,
+    pub estimated_size: Option<u64>,
+    /// Time at which a deferred request is started.
+    pub not_before: Option<DateTime<Local>>,
 }
 
 pub struct State {
+    url: Option<Url>,
     title: Option<String>,
     phase: Phase,
     queue: Vec<QueueItem>,
     error: Option<Error>,
+    error_time: Option<DateTime<Local>>,
+    verify_warning: Option<VerifyWarning>,
+    ffmpeg_status: Option<Result<String, FfmpegCheckError>>,
+    show_listing: Option<Result<Vec<ShowEpisode>, Error>>,
+    segment_listing: Option<Result<EpisodeSegments, Error>>,
 }
 
 impl State {
     pub fn new() -> Self {
         Self {
+            url: None,
             title: None,
             phase: Phase::Idle,
             queue: vec![],
             error: None,
+            error_time: None,
+            verify_warning: None,
+            ffmpeg_status: None,
+            show_listing: None,
+            segment_listing: None,
         }
     }
 
     pub fn update(&mut self, u: StateUpdate) {
         match u {
-            StateUpdate::StartedRequest { request_id: id } => {
+            StateUpdate::StartedRequest {
+                request_id: id,
+                url,
+            } => {
+                self.url = Some(url);
                 self.title = None;
                 self.error = None;
                 self.phase = Phase::Analyzing;
@@ -163,7 +362,21 @@ impl State {
                     *progress = p;
                 }
             }
+            StateUpdate::Deferred {
+                request_id,
+                url,
+                not_before,
+            } => {
+                self.error = None;
+                self.queue.push(QueueItem {
+                    request_id,
+                    title: self.title.clone().unwrap_or_else(|| url.to_string()),
+                    estimated_size: None,
+                    not_before: Some(not_before),
+                });
+            }
             StateUpdate::Idle => {
+                self.url = None;
                 self.title = None;
                 self.error = None;
                 self.phase = Phase::Idle;
@@ -171,17 +384,48 @@ impl State {
             StateUpdate::Merging => {
                 self.phase = Phase::Merging;
             }
+            StateUpdate::Enqueued { request_id, title } => {
+                self.queue.push(QueueItem {
+                    request_id,
+                    title,
+                    estimated_size: None,
+                    not_before: None,
+                });
+            }
             StateUpdate::StartedVideo {
                 video_no,
                 total_videos,
             } => {
                 self.phase = Phase::Downloading {
                     video_no: (video_no, total_videos),
-                    progress: 0_f32,
+                    progress: Progress::default(),
+                }
+            }
+            StateUpdate::Estimated {
+                request_id,
+                title,
+                size,
+            } => {
+                if let Some(q) = self.queue.iter_mut().find(|q| q.request_id == request_id) {
+                    q.title = title;
+                    q.estimated_size = Some(size);
                 }
             }
             StateUpdate::Error(e) => {
                 self.error = Some(e);
+                self.error_time = Some(Local::now());
+            }
+            StateUpdate::VerificationFailed(warning) => {
+                self.verify_warning = Some(warning);
+            }
+            StateUpdate::FfmpegChecked(res) => {
+                self.ffmpeg_status = Some(res);
+            }
+            StateUpdate::ShowListed(res) => {
+                self.show_listing = Some(res);
+            }
+            StateUpdate::SegmentsListed(res) => {
+                self.segment_listing = Some(res);
             }
         }
     }
@@ -201,6 +445,11 @@ impl State {
         self.queue.is_empty()
     }
 
+    /// URL of the current request.
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
     pub fn title(&self) -> Option<&str> {
         self.title.as_deref()
     }
@@ -213,9 +462,41 @@ impl State {
         self.error.as_ref()
     }
 
+    pub fn error_time(&self) -> Option<DateTime<Local>> {
+        self.error_time
+    }
+
+    /// The last download that did not pass the verification, until it is dismissed.
+    pub fn verify_warning(&self) -> Option<&VerifyWarning> {
+        self.verify_warning.as_ref()
+    }
+
+    pub fn dismiss_verify_warning(&mut self) {
+        self.verify_warning = None;
+    }
+
+    /// Takes the episodes of the last listed show, once they have arrived.
+    pub fn take_show_listing(&mut self) -> Option<Result<Vec<ShowEpisode>, Error>> {
+        self.show_listing.take()
+    }
+
+    /// Takes the segments of the last analyzed episode, once they have arrived.
+    pub fn take_segment_listing(&mut self) -> Option<Result<EpisodeSegments, Error>> {
+        self.segment_listing.take()
+    }
+
     pub fn has_error(&self) -> bool {
         self.error.is_some()
     }
+
+    /// Version of the configured ffmpeg, `None` while the check is still running.
+    pub fn ffmpeg_version(&self) -> Option<&str> {
+        self.ffmpeg_status.as_ref()?.as_deref().ok()
+    }
+
+    pub fn ffmpeg_error(&self) -> Option<&FfmpegCheckError> {
+        self.ffmpeg_status.as_ref()?.as_ref().err()
+    }
 }
 
 #[cfg(test)]
@@ -223,44 +504,43 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_valid_oonurl() {
-        let u1 = OonUrl::new("https://on.orf.at/video/14225330");
-        assert!(u1.is_ok());
-        let u2 = OonUrl::new(
-            "https://on.orf.at/video/14224991/willkommen-darmstadt-mit-stermann-grissemann",
-        );
-        assert!(u2.is_ok());
-    }
-
-    #[test]
-    fn test_invalid_oonurl() {
-        let u = OonUrl::new("https://example.com/foo/a");
-        assert!(u.is_err());
-    }
-
-    #[test]
-    fn test_video_id() {
-        let u1 = OonUrl::new("https://on.orf.at/video/14225330").unwrap();
-        assert_eq!(u1.video_id(), "14225330");
-        let u2 = OonUrl::new(
-            "https://on.orf.at/video/14224991/willkommen-darmstadt-mit-stermann-grissemann",
+    fn test_video_url() {
+        let u = VideoUrl::new(
+            "https://on.orf.at/video/14225651/15636092/gauder-fest-im-tiroler-zillertal",
         )
         .unwrap();
-        assert_eq!(u2.video_id(), "14224991");
+        assert_eq!(u.extractor().name(), "ORF On");
+        assert_eq!(u.video_id(), "14225651");
+        assert_eq!(u.segment_id(), &Some("15636092".to_owned()));
     }
 
     #[test]
-    fn test_segment_id() {
-        let u1 = OonUrl::new(
-            "https://on.orf.at/video/14224991/willkommen-darmstadt-mit-stermann-grissemann",
-        )
-        .unwrap();
-        assert_eq!(u1.segment_id(), &None);
+    fn test_invalid_video_url() {
+        assert!(VideoUrl::new("https://example.com/foo/a").is_err());
+        assert!(VideoUrl::new("on.orf.at/video/14225330").is_err());
+    }
 
-        let u2 = OonUrl::new(
-            "https://on.orf.at/video/14225651/15636092/gauder-fest-im-tiroler-zillertal",
-        )
-        .unwrap();
-        assert_eq!(u2.segment_id(), &Some("15636092".to_owned()));
+    #[test]
+    fn test_time_window() {
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let night = TimeWindow {
+            start: time(20),
+            end: time(6),
+        };
+        assert!(night.contains(time(23)));
+        assert!(night.contains(time(2)));
+        assert!(!night.contains(time(6)));
+        assert!(!night.contains(time(12)));
+
+        let morning = Local.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        assert_eq!(
+            night.next_start(morning),
+            Local.with_ymd_and_hms(2024, 3, 5, 20, 0, 0).unwrap()
+        );
+        let late_evening = Local.with_ymd_and_hms(2024, 3, 5, 21, 0, 0).unwrap();
+        assert_eq!(
+            night.next_start(late_evening),
+            Local.with_ymd_and_hms(2024, 3, 6, 20, 0, 0).unwrap()
+        );
     }
 }
@@ -0,0 +1,264 @@
+//! Minimal reader for the box structure of ISO base media (MP4) files.
+
+use std::fmt;
+use std::io::{self, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BoxError {
+    #[error("invalid box size at offset {0}")]
+    InvalidSize(usize),
+    #[error("box at offset {0} is truncated")]
+    Truncated(usize),
+}
+
+pub struct Mp4Box<'a> {
+    pub box_type: [u8; 4],
+    pub payload: &'a [u8],
+}
+
+impl Mp4Box<'_> {
+    pub fn is(&self, box_type: &str) -> bool {
+        self.box_type == box_type.as_bytes()
+    }
+}
+
+/// Splits `data` into consecutive boxes, which have to cover it completely.
+pub fn parse_boxes(data: &[u8]) -> Result<Vec<Mp4Box<'_>>, BoxError> {
+    let mut boxes = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+        if rest.len() < 8 {
+            return Err(BoxError::Truncated(offset));
+        }
+        let size = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as u64;
+        let box_type: [u8; 4] = rest[4..8].try_into().unwrap();
+        let (header_len, size) = match size {
+            0 => (8, rest.len() as u64),
+            1 => {
+                if rest.len() < 16 {
+                    return Err(BoxError::Truncated(offset));
+                }
+                (16, u64::from_be_bytes(rest[8..16].try_into().unwrap()))
+            }
+            _ => (8, size),
+        };
+        if size < header_len as u64 {
+            return Err(BoxError::InvalidSize(offset));
+        }
+        if size > rest.len() as u64 {
+            return Err(BoxError::Truncated(offset));
+        }
+
+        boxes.push(Mp4Box {
+            box_type,
+            payload: &rest[header_len..size as usize],
+        });
+        offset += size as usize;
+    }
+    Ok(boxes)
+}
+
+fn find_box<'a>(data: &'a [u8], box_type: &str) -> Result<Option<&'a [u8]>, BoxError> {
+    Ok(parse_boxes(data)?
+        .into_iter()
+        .find(|b| b.is(box_type))
+        .map(|b| b.payload))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().unwrap(),
+    ))
+}
+
+/// Reads the payload of the top level `moov` box of a file without loading the media data.
+pub async fn read_moov(path: &Path) -> Result<Option<Vec<u8>>, io::Error> {
+    let mut file = File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let mut offset = 0;
+    while offset + 8 <= len {
+        file.seek(SeekFrom::Start(offset)).await?;
+        let mut header = [0_u8; 16];
+        file.read_exact(&mut header[..8]).await?;
+        let (header_len, size) = match read_u32(&header, 0).unwrap() {
+            0 => (8, len - offset),
+            1 => {
+                file.read_exact(&mut header[8..]).await?;
+                (16, read_u64(&header, 8).unwrap())
+            }
+            size => (8, size as u64),
+        };
+        if size < header_len || offset + size > len {
+            return Ok(None);
+        }
+        if &header[4..8] == b"moov" {
+            let mut payload = vec![0_u8; (size - header_len) as usize];
+            file.read_exact(&mut payload).await?;
+            return Ok(Some(payload));
+        }
+        offset += size;
+    }
+    Ok(None)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TrackKind {
+    Video,
+    Audio,
+    Other,
+}
+
+impl fmt::Display for TrackKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TrackKind::Video => "video",
+            TrackKind::Audio => "audio",
+            TrackKind::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Track {
+    pub kind: TrackKind,
+    pub duration: Duration,
+}
+
+/// Reads kind and duration of the tracks from the payload of a `moov` box.
+pub fn parse_tracks(moov: &[u8]) -> Result<Vec<Track>, BoxError> {
+    let mut tracks = vec![];
+    for trak in parse_boxes(moov)?.into_iter().filter(|b| b.is("trak")) {
+        let Some(mdia) = find_box(trak.payload, "mdia")? else {
+            continue;
+        };
+        let kind = match find_box(mdia, "hdlr")?.and_then(|hdlr| hdlr.get(8..12)) {
+            Some(b"vide") => TrackKind::Video,
+            Some(b"soun") => TrackKind::Audio,
+            _ => TrackKind::Other,
+        };
+        let mdhd = find_box(mdia, "mdhd")?.unwrap_or_default();
+        // version 1 uses 64 bit times
+        let (timescale, duration) = if mdhd.first() == Some(&1) {
+            (read_u32(mdhd, 20), read_u64(mdhd, 24))
+        } else {
+            (read_u32(mdhd, 12), read_u32(mdhd, 16).map(u64::from))
+        };
+        let duration = match (timescale, duration) {
+            (Some(timescale), Some(duration)) if timescale > 0 => {
+                Duration::from_secs_f64(duration as f64 / timescale as f64)
+            }
+            _ => Duration::ZERO,
+        };
+        tracks.push(Track { kind, duration });
+    }
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::{movie, mp4_box};
+    use super::*;
+
+    #[test]
+    fn test_parse_boxes() {
+        let mut data = [mp4_box("ftyp", b"isom"), mp4_box("moov", &[1, 2, 3])].concat();
+        // box with 64 bit size
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&18_u64.to_be_bytes());
+        data.extend_from_slice(&[4, 5]);
+        // box extending to the end
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&[6]);
+
+        let boxes = parse_boxes(&data).unwrap();
+        let types = boxes.iter().map(|b| &b.box_type).collect::<Vec<_>>();
+        assert_eq!(types, vec![b"ftyp", b"moov", b"mdat", b"free"]);
+        assert_eq!(boxes[1].payload, &[1, 2, 3]);
+        assert_eq!(boxes[2].payload, &[4, 5]);
+        assert_eq!(boxes[3].payload, &[6]);
+    }
+
+    #[test]
+    fn test_parse_boxes_errors() {
+        let data = [mp4_box("moof", &[0; 10]), mp4_box("mdat", &[0; 10])].concat();
+        assert_eq!(
+            parse_boxes(&data[..data.len() - 1]).err(),
+            Some(BoxError::Truncated(18))
+        );
+        assert_eq!(
+            parse_boxes(&data[..20]).err(),
+            Some(BoxError::Truncated(18))
+        );
+        assert_eq!(
+            parse_boxes(b"\0\0\0\x04moov").err(),
+            Some(BoxError::InvalidSize(0))
+        );
+        assert_eq!(
+            parse_boxes(b"<!DOCTYPE html>").err(),
+            Some(BoxError::Truncated(0))
+        );
+    }
+
+    #[test]
+    fn test_parse_tracks() {
+        let moov = movie(&[
+            (b"vide", 90_000, 90_000 * 60),
+            (b"soun", 48_000, 48_000 * 59),
+            (b"text", 1_000, 0),
+        ]);
+        let tracks = parse_tracks(parse_boxes(&moov).unwrap()[0].payload).unwrap();
+        assert_eq!(
+            tracks,
+            vec![
+                Track {
+                    kind: TrackKind::Video,
+                    duration: Duration::from_secs(60)
+                },
+                Track {
+                    kind: TrackKind::Audio,
+                    duration: Duration::from_secs(59)
+                },
+                Track {
+                    kind: TrackKind::Other,
+                    duration: Duration::ZERO
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_moov() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.mp4");
+        let moov = mp4_box("moov", &[7; 20]);
+        let data = [
+            mp4_box("ftyp", b"isom"),
+            mp4_box("mdat", &[0; 1_000]),
+            moov.clone(),
+        ]
+        .concat();
+        tokio::fs::write(&path, &data).await.unwrap();
+        assert_eq!(read_moov(&path).await.unwrap(), Some(vec![7; 20]));
+
+        tokio::fs::write(&path, &data[..data.len() - moov.len()])
+            .await
+            .unwrap();
+        assert_eq!(read_moov(&path).await.unwrap(), None);
+    }
+}
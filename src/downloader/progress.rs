@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const SPEED_WINDOW: Duration = Duration::from_secs(5);
+const UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Progress {
+    pub downloaded: u64,
+    pub total: u64,
+    pub bytes_per_sec: u64,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            0_f32
+        } else {
+            (self.downloaded as f64 / self.total as f64).min(1_f64) as f32
+        }
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total.saturating_sub(self.downloaded);
+        remaining
+            .checked_div(self.bytes_per_sec)
+            .map(Duration::from_secs)
+    }
+}
+
+pub enum ChunkEvent {
+    Started {
+        content_length: Option<u64>,
+    },
+    Received(u64),
+    Finished {
+        content_length: Option<u64>,
+        size: u64,
+    },
+    /// The chunk failed and will be downloaded again.
+    Discarded {
+        content_length: Option<u64>,
+        received: u64,
+    },
+}
+
+struct StreamProgress {
+    total_chunks: usize,
+    finished_chunks: usize,
+    finished_bytes: u64,
+    /// Sum of the announced lengths of the chunks currently being downloaded.
+    pending_bytes: u64,
+    received: u64,
+    estimate: u64,
+}
+
+impl StreamProgress {
+    fn total(&self) -> u64 {
+        let unfinished = (self.total_chunks - self.finished_chunks) as u64;
+        if unfinished == 0 {
+            return self.finished_bytes;
+        }
+
+        // The first chunk only contains the initialization segment, so the average chunk size is
+        // not meaningful before a few more chunks have been downloaded.
+        let expected = if self.finished_chunks >= 3 {
+            self.finished_bytes + self.finished_bytes / self.finished_chunks as u64 * unfinished
+        } else {
+            self.estimate
+        };
+        let in_flight = self.received - self.finished_bytes;
+        expected.max(self.finished_bytes + in_flight.max(self.pending_bytes))
+    }
+}
+
+/// Tracks the downloaded bytes of the streams of a video.
+pub struct ProgressTracker {
+    streams: Vec<StreamProgress>,
+    samples: VecDeque<(Instant, u64)>,
+    last_update: Option<Instant>,
+}
+
+impl ProgressTracker {
+    /// Creates a tracker for streams given by their number of chunks and estimated size.
+    pub fn new(streams: &[(usize, u64)]) -> Self {
+        Self {
+            streams: streams
+                .iter()
+                .map(|(total_chunks, estimate)| StreamProgress {
+                    total_chunks: *total_chunks,
+                    finished_chunks: 0,
+                    finished_bytes: 0,
+                    pending_bytes: 0,
+                    received: 0,
+                    estimate: *estimate,
+                })
+                .collect(),
+            samples: VecDeque::new(),
+            last_update: None,
+        }
+    }
+
+    fn downloaded(&self) -> u64 {
+        self.streams.iter().map(|s| s.received).sum()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.streams
+            .iter()
+            .all(|s| s.finished_chunks == s.total_chunks)
+    }
+
+    fn bytes_per_sec(&self) -> u64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((first_time, first_bytes)), Some((last_time, last_bytes))) => {
+                let secs = last_time.duration_since(*first_time).as_secs_f64();
+                if secs > 0_f64 {
+                    (last_bytes.saturating_sub(*first_bytes) as f64 / secs) as u64
+                } else {
+                    0
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            downloaded: self.downloaded(),
+            total: self.streams.iter().map(|s| s.total()).sum(),
+            bytes_per_sec: self.bytes_per_sec(),
+        }
+    }
+
+    /// Applies an event of the stream with index `stream`. Returns the current progress if it
+    /// should be reported.
+    pub fn update(&mut self, stream: usize, event: ChunkEvent, now: Instant) -> Option<Progress> {
+        let s = &mut self.streams[stream];
+        match event {
+            ChunkEvent::Started { content_length } => {
+                s.pending_bytes += content_length.unwrap_or(0);
+            }
+            ChunkEvent::Received(n) => {
+                s.received += n;
+            }
+            ChunkEvent::Finished {
+                content_length,
+                size,
+            } => {
+                s.pending_bytes -= content_length.unwrap_or(0);
+                s.finished_chunks += 1;
+                s.finished_bytes += size;
+            }
+            ChunkEvent::Discarded {
+                content_length,
+                received,
+            } => {
+                s.pending_bytes -= content_length.unwrap_or(0);
+                s.received -= received;
+            }
+        }
+
+        let downloaded = self.downloaded();
+        self.samples.push_back((now, downloaded));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) > SPEED_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        let due = self
+            .last_update
+            .is_none_or(|t| now.duration_since(t) >= UPDATE_INTERVAL);
+        if due || self.is_finished() {
+            self.last_update = Some(now);
+            Some(self.progress())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download_chunk(
+        t: &mut ProgressTracker,
+        stream: usize,
+        size: u64,
+        now: Instant,
+    ) -> Option<Progress> {
+        t.update(
+            stream,
+            ChunkEvent::Started {
+                content_length: Some(size),
+            },
+            now,
+        );
+        t.update(stream, ChunkEvent::Received(size), now);
+        t.update(
+            stream,
+            ChunkEvent::Finished {
+                content_length: Some(size),
+                size,
+            },
+            now,
+        )
+    }
+
+    #[test]
+    fn test_total_uses_estimate_first() {
+        let mut t = ProgressTracker::new(&[(11, 10_000), (11, 1_000)]);
+        let now = Instant::now();
+        download_chunk(&mut t, 0, 100, now);
+
+        let p = t.progress();
+        assert_eq!(p.downloaded, 100);
+        assert_eq!(p.total, 11_000);
+    }
+
+    #[test]
+    fn test_total_is_extrapolated() {
+        let mut t = ProgressTracker::new(&[(10, 1_000)]);
+        let now = Instant::now();
+        for _ in 0..5 {
+            download_chunk(&mut t, 0, 200, now);
+        }
+        assert_eq!(t.progress().total, 2_000);
+    }
+
+    #[test]
+    fn test_total_is_exact_when_finished() {
+        let mut t = ProgressTracker::new(&[(2, 1_000), (1, 1_000)]);
+        let now = Instant::now();
+        download_chunk(&mut t, 0, 300, now);
+        download_chunk(&mut t, 0, 400, now);
+        let p = download_chunk(&mut t, 1, 50, now).unwrap();
+        assert_eq!(p.downloaded, 750);
+        assert_eq!(p.total, 750);
+        assert_eq!(p.fraction(), 1_f32);
+    }
+
+    #[test]
+    fn test_speed_and_eta() {
+        let mut t = ProgressTracker::new(&[(1, 10_000)]);
+        let start = Instant::now();
+        t.update(
+            0,
+            ChunkEvent::Started {
+                content_length: Some(10_000),
+            },
+            start,
+        );
+        t.update(0, ChunkEvent::Received(1_000), start);
+        let p = t
+            .update(
+                0,
+                ChunkEvent::Received(2_000),
+                start + Duration::from_secs(2),
+            )
+            .unwrap();
+        assert_eq!(p.bytes_per_sec, 1_500);
+        assert_eq!(p.eta(), Some(Duration::from_secs(4)));
+    }
+
+    #[test]
+    fn test_updates_are_throttled() {
+        let mut t = ProgressTracker::new(&[(2, 10_000)]);
+        let start = Instant::now();
+        assert!(t.update(0, ChunkEvent::Received(1), start).is_some());
+        assert!(t
+            .update(
+                0,
+                ChunkEvent::Received(1),
+                start + Duration::from_millis(10)
+            )
+            .is_none());
+        assert!(t
+            .update(
+                0,
+                ChunkEvent::Received(1),
+                start + Duration::from_millis(300)
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn test_discarded_chunk() {
+        let mut t = ProgressTracker::new(&[(2, 1_000)]);
+        let now = Instant::now();
+        t.update(
+            0,
+            ChunkEvent::Started {
+                content_length: Some(600),
+            },
+            now,
+        );
+        t.update(0, ChunkEvent::Received(300), now);
+        t.update(
+            0,
+            ChunkEvent::Discarded {
+                content_length: Some(600),
+                received: 300,
+            },
+            now + Duration::from_secs(1),
+        );
+        download_chunk(&mut t, 0, 500, now + Duration::from_secs(2));
+        let p = download_chunk(&mut t, 0, 500, now + Duration::from_secs(2)).unwrap();
+        assert_eq!(p.downloaded, 1_000);
+        assert_eq!(p.total, 1_000);
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+use tokio::time::sleep;
+
+use super::settings::{RateLimitRule, Settings};
+
+struct Bucket {
+    default_limit: Option<u64>,
+    schedule: Vec<RateLimitRule>,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn limit_at(&self, time: NaiveTime) -> Option<u64> {
+        self.schedule
+            .iter()
+            .find(|r| r.contains(time))
+            .map_or(self.default_limit, |r| r.limit)
+    }
+}
+
+/// Token bucket limiting the download rate of all requests together. The limit is taken from
+/// the settings and can be changed while downloading.
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                default_limit: settings.rate_limit,
+                schedule: settings.rate_limit_schedule.clone(),
+                tokens: 0_f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn configure(&self, settings: &Settings) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.default_limit = settings.rate_limit;
+        bucket.schedule = settings.rate_limit_schedule.clone();
+    }
+
+    /// Takes `n` bytes from the bucket and returns how long to wait until they are covered.
+    fn reserve(&self, n: u64, now: Instant, time: NaiveTime) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.last_refill = now;
+
+        let Some(limit) = bucket.limit_at(time) else {
+            bucket.tokens = 0_f64;
+            return Duration::ZERO;
+        };
+        // allow bursts of at most one second
+        let rate = limit.max(1) as f64;
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate) - n as f64;
+        if bucket.tokens < 0_f64 {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    pub async fn acquire(&self, n: u64) {
+        let wait = self.reserve(n, Instant::now(), Local::now().time());
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    fn settings(rate_limit: Option<u64>) -> Settings {
+        Settings {
+            rate_limit,
+            rate_limit_schedule: vec![
+                RateLimitRule {
+                    start: time(8, 0),
+                    end: time(17, 0),
+                    limit: Some(2_000_000),
+                },
+                RateLimitRule {
+                    start: time(22, 0),
+                    end: time(6, 0),
+                    limit: None,
+                },
+            ],
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn test_limit_at() {
+        let l = RateLimiter::new(&settings(Some(5_000_000)));
+        let bucket = l.bucket.lock().unwrap();
+        assert_eq!(bucket.limit_at(time(9, 30)), Some(2_000_000));
+        assert_eq!(bucket.limit_at(time(17, 0)), Some(5_000_000));
+        assert_eq!(bucket.limit_at(time(23, 0)), None);
+        assert_eq!(bucket.limit_at(time(3, 0)), None);
+        assert_eq!(bucket.limit_at(time(6, 0)), Some(5_000_000));
+    }
+
+    #[test]
+    fn test_reserve() {
+        let l = RateLimiter::new(&settings(Some(1_000)));
+        let start = l.bucket.lock().unwrap().last_refill;
+        let evening = time(19, 0);
+
+        assert_eq!(l.reserve(500, start, evening), Duration::from_millis(500));
+        assert_eq!(l.reserve(500, start, evening), Duration::from_millis(1_000));
+        // the debt has been paid off after a second, tokens don't accumulate beyond one second
+        let later = start + Duration::from_secs(10);
+        assert_eq!(l.reserve(1_000, later, evening), Duration::ZERO);
+        assert_eq!(
+            l.reserve(4_000_000, later, time(12, 0)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(l.reserve(u64::MAX, later, time(23, 0)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_configure() {
+        let l = RateLimiter::new(&Settings::default());
+        let start = l.bucket.lock().unwrap().last_refill;
+        assert_eq!(l.reserve(1_000, start, time(12, 0)), Duration::ZERO);
+
+        l.configure(&Settings {
+            rate_limit: Some(1_000),
+            ..Settings::default()
+        });
+        assert_eq!(l.reserve(2_000, start, time(12, 0)), Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,230 @@
+//! Subscriptions to shows, whose new episodes are downloaded automatically.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::http::HttpClient;
+use super::{Error, Quality, ShowEpisode, ShowUrl};
+
+/// Federal state, for shows with regional editions like "Bundesland heute".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Region {
+    Burgenland,
+    Kaernten,
+    Niederoesterreich,
+    Oberoesterreich,
+    Salzburg,
+    Steiermark,
+    Tirol,
+    Vorarlberg,
+    Wien,
+}
+
+impl Region {
+    pub const ALL: [Region; 9] = [
+        Region::Burgenland,
+        Region::Kaernten,
+        Region::Niederoesterreich,
+        Region::Oberoesterreich,
+        Region::Salzburg,
+        Region::Steiermark,
+        Region::Tirol,
+        Region::Vorarlberg,
+        Region::Wien,
+    ];
+
+    /// Name as it appears in the titles of the regional editions.
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::Burgenland => "Burgenland",
+            Region::Kaernten => "Kärnten",
+            Region::Niederoesterreich => "Niederösterreich",
+            Region::Oberoesterreich => "Oberösterreich",
+            Region::Salzburg => "Salzburg",
+            Region::Steiermark => "Steiermark",
+            Region::Tirol => "Tirol",
+            Region::Vorarlberg => "Vorarlberg",
+            Region::Wien => "Wien",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subscription {
+    /// Url of the profile page of the show.
+    pub url: String,
+    pub enabled: bool,
+    /// Regular expression the title has to match, empty to accept all.
+    pub title_filter: String,
+    /// Only take the regional edition of this federal state.
+    pub region: Option<Region>,
+    /// Minimum duration in minutes, to skip short clips.
+    pub min_duration: Option<u64>,
+    pub quality: Quality,
+    pub dest_dir: PathBuf,
+    /// Only episodes broadcast after this time are downloaded.
+    pub since: DateTime<Local>,
+}
+
+impl Subscription {
+    pub fn new(url: &ShowUrl, quality: Quality, dest_dir: PathBuf) -> Self {
+        Self {
+            url: url.as_str().to_owned(),
+            enabled: true,
+            title_filter: String::new(),
+            region: None,
+            min_duration: None,
+            quality,
+            dest_dir,
+            since: Local::now(),
+        }
+    }
+
+    pub fn filter(&self) -> Result<EpisodeFilter, regex::Error> {
+        let title = if self.title_filter.trim().is_empty() {
+            None
+        } else {
+            Some(
+                RegexBuilder::new(self.title_filter.trim())
+                    .case_insensitive(true)
+                    .build()?,
+            )
+        };
+        let region = self
+            .region
+            .map(|r| Regex::new(&format!(r"\b{}\b", regex::escape(r.name()))).unwrap());
+        Ok(EpisodeFilter {
+            title,
+            region,
+            min_duration: self.min_duration.map(|m| Duration::from_secs(m * 60)),
+            since: self.since,
+        })
+    }
+}
+
+/// Compiled filters of a subscription. Episodes lacking the duration are not filtered by it,
+/// episodes lacking the date are rejected, as they might be old ones of the back catalogue.
+pub struct EpisodeFilter {
+    title: Option<Regex>,
+    region: Option<Regex>,
+    min_duration: Option<Duration>,
+    since: DateTime<Local>,
+}
+
+impl EpisodeFilter {
+    pub fn matches(&self, episode: &ShowEpisode) -> bool {
+        self.title
+            .as_ref()
+            .is_none_or(|re| re.is_match(&episode.title))
+            && self
+                .region
+                .as_ref()
+                .is_none_or(|re| re.is_match(&episode.title))
+            && self
+                .min_duration
+                .is_none_or(|min| episode.duration.is_none_or(|d| d >= min))
+            && episode.date.is_some_and(|d| d >= self.since)
+    }
+}
+
+/// Lists the episodes of the subscribed show that pass its filters and are not known yet.
+pub(super) async fn check(
+    http_client: &HttpClient,
+    subscription: &Subscription,
+    is_known: impl Fn(&ShowEpisode) -> bool,
+) -> Result<Vec<ShowEpisode>, Error> {
+    let filter = subscription
+        .filter()
+        .map_err(|e| anyhow::anyhow!("invalid title filter: {}", e))?;
+    let url = ShowUrl::new(&subscription.url)
+        .map_err(|_| anyhow::anyhow!("invalid show url {}", subscription.url))?;
+    let episodes = url.extractor().list_episodes(http_client, &url).await?;
+    Ok(episodes
+        .into_iter()
+        .filter(|e| filter.matches(e) && !is_known(e))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::downloader::VideoUrl;
+
+    fn subscription() -> Subscription {
+        Subscription {
+            url: "https://on.orf.at/profile/70018/bundesland-heute".to_owned(),
+            enabled: true,
+            title_filter: String::new(),
+            region: None,
+            min_duration: None,
+            quality: Quality::High,
+            dest_dir: PathBuf::from("/tmp"),
+            since: Local.with_ymd_and_hms(2024, 4, 23, 0, 0, 0).unwrap(),
+        }
+    }
+
+    fn episode(title: &str, day: u32, minutes: Option<u64>) -> ShowEpisode {
+        ShowEpisode {
+            url: VideoUrl::new("https://on.orf.at/video/14226001").unwrap(),
+            title: title.to_owned(),
+            date: Some(Local.with_ymd_and_hms(2024, 4, day, 19, 0, 0).unwrap()),
+            duration: minutes.map(|m| Duration::from_secs(m * 60)),
+        }
+    }
+
+    #[test]
+    fn test_filter_since() {
+        let filter = subscription().filter().unwrap();
+        assert!(filter.matches(&episode("Wien heute", 24, Some(20))));
+        assert!(!filter.matches(&episode("Wien heute", 22, Some(20))));
+
+        let undated = ShowEpisode {
+            date: None,
+            ..episode("Wien heute", 24, Some(20))
+        };
+        assert!(!filter.matches(&undated));
+    }
+
+    #[test]
+    fn test_filter_region() {
+        let filter = Subscription {
+            region: Some(Region::Tirol),
+            ..subscription()
+        }
+        .filter()
+        .unwrap();
+        assert!(filter.matches(&episode("Tirol heute vom 24.04.2024", 24, None)));
+        assert!(!filter.matches(&episode("Südtirol heute vom 24.04.2024", 24, None)));
+        assert!(!filter.matches(&episode("Wien heute vom 24.04.2024", 24, None)));
+    }
+
+    #[test]
+    fn test_filter_title_and_duration() {
+        let filter = Subscription {
+            title_filter: "^zib 1".to_owned(),
+            min_duration: Some(10),
+            ..subscription()
+        }
+        .filter()
+        .unwrap();
+        assert!(filter.matches(&episode("ZIB 1 vom 24.04.2024", 24, Some(18))));
+        assert!(filter.matches(&episode("ZIB 1 vom 24.04.2024", 24, None)));
+        assert!(!filter.matches(&episode("ZIB 1 vom 24.04.2024", 24, Some(3))));
+        assert!(!filter.matches(&episode("ZIB 2 vom 24.04.2024", 24, Some(18))));
+    }
+
+    #[test]
+    fn test_invalid_title_filter() {
+        let s = Subscription {
+            title_filter: "(unclosed".to_owned(),
+            ..subscription()
+        };
+        assert!(s.filter().is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Minimal HTTP server for tests, answering requests with fixed responses by path.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::time::sleep;
+use url::Url;
+
+/// Builds an MP4 box with the given type and payload.
+pub fn mp4_box(box_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut b = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    b.extend_from_slice(box_type.as_bytes());
+    b.extend_from_slice(payload);
+    b
+}
+
+pub fn init_segment() -> Vec<u8> {
+    [mp4_box("ftyp", b"isom"), mp4_box("moov", &[])].concat()
+}
+
+pub fn media_segment(data: &[u8]) -> Vec<u8> {
+    [mp4_box("moof", &[]), mp4_box("mdat", data)].concat()
+}
+
+/// Builds a `moov` box with tracks given by handler type, timescale and duration.
+pub fn movie(tracks: &[(&[u8; 4], u32, u32)]) -> Vec<u8> {
+    let traks = tracks
+        .iter()
+        .map(|(handler, timescale, duration)| {
+            let mut mdhd = vec![0_u8; 12];
+            mdhd.extend_from_slice(&timescale.to_be_bytes());
+            mdhd.extend_from_slice(&duration.to_be_bytes());
+            mdhd.extend_from_slice(&[0; 4]);
+            let mut hdlr = vec![0_u8; 8];
+            hdlr.extend_from_slice(*handler);
+            hdlr.extend_from_slice(&[0; 13]);
+            let mdia = [mp4_box("mdhd", &mdhd), mp4_box("hdlr", &hdlr)].concat();
+            mp4_box("trak", &mp4_box("mdia", &mdia))
+        })
+        .collect::<Vec<_>>();
+    mp4_box("moov", &traks.concat())
+}
+
+#[derive(Clone)]
+pub struct Route {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+    pub delay: Duration,
+}
+
+impl Route {
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body: body.into(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            content_type: "text/html",
+            body: vec![],
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+pub struct TestServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    pub async fn start(routes: Vec<(&str, Route)>) -> Self {
+        let routes: Arc<HashMap<String, Route>> = Arc::new(
+            routes
+                .into_iter()
+                .map(|(path, route)| (path.to_owned(), route))
+                .collect(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let requests_clone = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let routes = routes.clone();
+                let requests = requests_clone.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![];
+                    let mut buf = [0_u8; 1024];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buf[..n]);
+                    }
+                    let request = String::from_utf8_lossy(&request).into_owned();
+                    let path = request.split_whitespace().nth(1).unwrap_or_default();
+                    let route = routes.get(path).cloned().unwrap_or(Route::status(404));
+                    requests.lock().unwrap().push(request.clone());
+
+                    sleep(route.delay).await;
+                    let head = format!(
+                        "HTTP/1.1 {} X\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+                         Connection: close\r\n\r\n",
+                        route.status,
+                        route.content_type,
+                        route.body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&route.body).await;
+                });
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    /// Heads of the requests received so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn url(&self, path: &str) -> Url {
+        Url::parse(&format!("http://{}{}", self.addr, path)).unwrap()
+    }
+}
@@ -0,0 +1,130 @@
+use egui::{Align2, Pos2, RichText};
+
+use super::{format_size, SPACE, SPACE_2, SPACE_4};
+use crate::downloader::{HistoryEntry, Quality};
+
+pub enum HistoryAction {
+    Remove(HistoryEntry),
+    Close,
+}
+
+fn quality_name(quality: Quality) -> &'static str {
+    match quality {
+        Quality::High => "Hoch",
+        Quality::Medium => "Mittel",
+        Quality::Low => "Niedrig",
+    }
+}
+
+/// Browses and searches the finished downloads, newest first.
+pub struct HistoryWindow {
+    entries: Vec<HistoryEntry>,
+    query: String,
+}
+
+impl HistoryWindow {
+    pub fn new(mut entries: Vec<HistoryEntry>) -> Self {
+        entries.reverse();
+        Self {
+            entries,
+            query: String::new(),
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<HistoryAction> {
+        let mut action = None;
+
+        egui::Window::new("Verlauf")
+            .collapsible(false)
+            .resizable(false)
+            .pivot(Align2::CENTER_TOP)
+            .fixed_pos(Pos2::new(300.0, 30.0))
+            .show(ctx, |ui| {
+                ui.set_width(450.0);
+                ui.add_space(SPACE_2);
+                ui.horizontal(|ui| {
+                    let search_label = ui.label("Suche:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.query)
+                            .desired_width(f32::INFINITY)
+                            .hint_text("Titel, Video-ID oder Pfad"),
+                    )
+                    .labelled_by(search_label.id);
+                });
+                ui.add_space(SPACE_4);
+
+                let query = &self.query;
+                let mut found = self.entries.iter().filter(|e| e.matches(query)).peekable();
+                if found.peek().is_none() {
+                    ui.label(if self.entries.is_empty() {
+                        "Noch nichts heruntergeladen."
+                    } else {
+                        "Keine Treffer."
+                    });
+                } else {
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            for entry in found {
+                                ui.group(|ui| {
+                                    ui.set_width(ui.available_width());
+                                    ui.horizontal(|ui| {
+                                        let mut title = entry.title.clone();
+                                        if let Some(segment_ids) = &entry.segment_ids {
+                                            title.push_str(&format!(
+                                                " (Auswahl: {} Teile)",
+                                                segment_ids.len()
+                                            ));
+                                        }
+                                        ui.label(RichText::new(title).strong());
+                                        ui.with_layout(
+                                            egui::Layout::right_to_left(egui::Align::Center),
+                                            |ui| {
+                                                if ui
+                                                    .button("🗑")
+                                                    .on_hover_text("Aus dem Verlauf entfernen")
+                                                    .clicked()
+                                                {
+                                                    action =
+                                                        Some(HistoryAction::Remove(entry.clone()));
+                                                }
+                                            },
+                                        );
+                                    });
+                                    let mut video = format!("Video {}", entry.video_id);
+                                    if let Some(segment_id) = &entry.segment_id {
+                                        video.push_str(&format!(", Teil {}", segment_id));
+                                    }
+                                    let mut details = vec![
+                                        entry.date.format("%d.%m.%Y %H:%M").to_string(),
+                                        video,
+                                    ];
+                                    if let Some(quality) = entry.quality {
+                                        details.push(format!("Qualität {}", quality_name(quality)));
+                                    }
+                                    if let Some(size) = entry.size {
+                                        details.push(format_size(size));
+                                    }
+                                    ui.label(details.join(" – "));
+                                    if let Some(path) = &entry.path {
+                                        ui.label(RichText::new(path.to_string_lossy()).weak());
+                                    }
+                                });
+                                ui.add_space(SPACE_2);
+                            }
+                        });
+                }
+
+                ui.add_space(SPACE_4);
+                if ui.button("Schließen").clicked() {
+                    action = Some(HistoryAction::Close);
+                }
+                ui.add_space(SPACE);
+            });
+
+        if let Some(HistoryAction::Remove(entry)) = &action {
+            self.entries.retain(|e| e != entry);
+        }
+        action
+    }
+}
@@ -0,0 +1,910 @@
+mod history;
+mod segments;
+mod settings;
+mod show;
+mod subscriptions;
+
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use arboard::Clipboard;
+use chrono::Local;
+use directories::UserDirs;
+use eframe::glow::Context;
+use egui::{vec2, Align2, Pos2, RichText, Ui, Vec2};
+use egui_file::FileDialog;
+use permissions::is_writable;
+use serde::{Deserialize, Serialize};
+
+use self::history::{HistoryAction, HistoryWindow};
+use self::segments::{SegmentsAction, SegmentsWindow};
+use self::settings::{SettingsAction, SettingsWindow};
+use self::show::{ShowAction, ShowWindow};
+use self::subscriptions::{SubscriptionsAction, SubscriptionsWindow};
+use super::downloader::{
+    ChunkError, Client, DownloadRequest, Error, FfmpegCheckError, HistoryEntry, HttpSettingsError,
+    Phase, Quality, SegmentOutput, Settings, ShowUrl, State, StateUpdate, TrackKind, VerifyError,
+    VideoUrl,
+};
+
+const SPACE: f32 = 3.0;
+const SPACE_2: f32 = 6.0;
+const SPACE_4: f32 = 12.0;
+const SETTINGS_KEY: &str = "settings";
+
+pub fn load_settings(cc: &eframe::CreationContext<'_>) -> Settings {
+    cc.storage
+        .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+        .unwrap_or_default()
+}
+
+fn uneditable_textedit(ui: &mut egui::Ui, mut text: &str) {
+    ui.add_sized(
+        ui.available_size(),
+        egui::TextEdit::singleline(&mut text).desired_width(f32::INFINITY),
+    );
+}
+
+/// Formats a size in bytes with decimal units, e.g. `1,5 GB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    let number = if unit == 0 || size >= 100.0 {
+        format!("{:.0}", size)
+    } else {
+        format!("{:.1}", size)
+    };
+    format!("{} {}", number.replace('.', ","), UNITS[unit])
+}
+
+fn segment_output_edit(ui: &mut Ui, output: &mut SegmentOutput) {
+    ui.horizontal(|ui| {
+        ui.radio_value(output, SegmentOutput::Combined, "Eine Datei");
+        ui.radio_value(output, SegmentOutput::Separate, "Einzeln")
+            .on_hover_text("Nummeriert in einem Ordner mit dem Namen der Sendung");
+        ui.radio_value(output, SegmentOutput::Both, "Beides");
+    });
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{} h {} min", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{} min", (secs + 30) / 60)
+    } else {
+        format!("{} s", secs)
+    }
+}
+
+fn error_message(e: &Error) -> Cow<'static, str> {
+    match e {
+        Error::NetworkError(e) if e.is_timeout() => {
+            "Zeitüberschreitung bei der Verbindung zum Server. Bitte die Internetverbindung prüfen \
+             und erneut versuchen."
+                .into()
+        }
+        Error::NetworkError(e) if e.is_connect() => {
+            "Der Server ist nicht erreichbar. Bitte die Internetverbindung und die \
+             Proxy-Einstellungen prüfen."
+                .into()
+        }
+        Error::NetworkError(_) => "Ein Netzwerkfehler ist aufgetreten.".into(),
+        Error::HttpStatusError {
+            status: 404 | 410, ..
+        } => "Das Video wurde nicht gefunden. Vermutlich ist es nicht mehr verfügbar.".into(),
+        Error::HttpStatusError { status: 403, .. } => "Der Zugriff wurde verweigert.".into(),
+        Error::GeoBlockedError { .. } => {
+            "Dieses Video ist nur in Österreich abrufbar. Der Zugriff von einer ausländischen \
+             IP-Adresse wurde verweigert."
+                .into()
+        }
+        Error::HttpStatusError { status: 429, .. } => {
+            "Der Server meldet zu viele Anfragen. Bitte später erneut versuchen.".into()
+        }
+        Error::HttpStatusError { status, .. } if *status >= 500 => format!(
+            "Der Server hat einen Fehler gemeldet ({}). Bitte später erneut versuchen.",
+            status
+        )
+        .into(),
+        Error::HttpStatusError { status, .. } => {
+            format!("Der Server hat die Anfrage abgelehnt ({}).", status).into()
+        }
+        Error::YouthProtectionError { window } => format!(
+            "Dieses Video ist aus Jugendschutzgründen nur zwischen {} und {} Uhr abrufbar.",
+            window.start.format("%H:%M"),
+            window.end.format("%H:%M")
+        )
+        .into(),
+        Error::HttpSettingsError(e) => format!(
+            "Ungültige Netzwerkeinstellungen: {}",
+            http_settings_error_message(e)
+        )
+        .into(),
+        Error::ScrapingError { .. } => {
+            "Auf der Seite wurde kein Video gefunden. Bitte die URL prüfen, eventuell wurde die \
+             ORF-Seite geändert."
+                .into()
+        }
+        Error::MpdError { .. } => {
+            "Die Videobeschreibung (MPD) des ORF konnte nicht gelesen werden.".into()
+        }
+        Error::InvalidChunkError { reason, .. } => format!(
+            "Ein Teil des Videos wurde fehlerhaft übertragen: {}",
+            chunk_error_message(reason)
+        )
+        .into(),
+        Error::VerificationError(e) => verify_error_message(e).into(),
+        Error::FileError(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            "Keine Berechtigung zum Schreiben. Bitte einen anderen Ordner wählen.".into()
+        }
+        Error::FileError(_) => "Fehler beim schreiben einer Datei.".into(),
+        Error::DiskFullError(_) => {
+            "Der Datenträger ist voll. Bitte Speicherplatz freigeben und erneut versuchen.".into()
+        }
+        Error::FfmpegError(e) if e.is_disk_full() => {
+            "Der Datenträger ist voll. Bitte Speicherplatz freigeben und erneut versuchen.".into()
+        }
+        Error::DiskSpaceError {
+            dir,
+            required,
+            available,
+        } => format!(
+            "Nicht genügend Speicherplatz in {}: benötigt ca. {}, verfügbar {}.",
+            dir.to_string_lossy(),
+            format_size(*required),
+            format_size(*available)
+        )
+        .into(),
+        Error::FfmpegMissingError(_) => "ffmpeg ist nicht verfügbar.".into(),
+        Error::FfmpegError(_) => "ffmpeg konnte die Videodatei nicht erstellen.".into(),
+        Error::UnexpectedError(_) => "Es ist ein unerwarteter Fehler aufgetreten.".into(),
+    }
+}
+
+fn phase_name(phase: Phase) -> String {
+    match phase {
+        Phase::Idle => "Bereit".to_owned(),
+        Phase::Analyzing => "Analysieren".to_owned(),
+        Phase::Downloading { video_no, .. } => {
+            format!("Herunterladen (Video {} von {})", video_no.0, video_no.1)
+        }
+        Phase::Merging => "Zusammenfügen".to_owned(),
+    }
+}
+
+/// Describes the current error with its causes and context, for attaching to bug reports.
+fn error_report(state: &State) -> Option<String> {
+    let e = state.error()?;
+    let mut report = format!("oondl {}\n", env!("CARGO_PKG_VERSION"));
+    if let Some(time) = state.error_time() {
+        writeln!(
+            report,
+            "Zeitpunkt: {}",
+            time.format("%Y-%m-%d %H:%M:%S %:z")
+        )
+        .unwrap();
+    }
+    if let Some(url) = state.url() {
+        writeln!(report, "URL: {}", url).unwrap();
+    }
+    if let Some(title) = state.title() {
+        writeln!(report, "Titel: {}", title).unwrap();
+    }
+    writeln!(report, "Phase: {}", phase_name(state.phase())).unwrap();
+    report.push_str("Fehler:\n");
+    let mut source: Option<&dyn std::error::Error> = Some(e);
+    while let Some(s) = source {
+        writeln!(report, "  {}", s).unwrap();
+        source = s.source();
+    }
+    Some(report)
+}
+
+/// Formats a duration as `h:mm:ss`.
+fn format_clock(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+fn verify_error_message(e: &VerifyError) -> String {
+    let track_name = |kind: &TrackKind| match kind {
+        TrackKind::Video => "Videospur",
+        TrackKind::Audio => "Tonspur",
+        TrackKind::Other => "Spur",
+    };
+    match e {
+        VerifyError::MissingMovie | VerifyError::InvalidMovie(_) => {
+            "Die heruntergeladene Datei ist beschädigt.".to_owned()
+        }
+        VerifyError::MissingTrack(kind) => {
+            format!(
+                "Der heruntergeladenen Datei fehlt die {}.",
+                track_name(kind)
+            )
+        }
+        VerifyError::Duration {
+            kind,
+            expected,
+            actual,
+        } => format!(
+            "Die {} der heruntergeladenen Datei ist {} statt {} lang.",
+            track_name(kind),
+            format_clock(*actual),
+            format_clock(*expected)
+        ),
+    }
+}
+
+fn chunk_error_message(e: &ChunkError) -> String {
+    match e {
+        ChunkError::ContentType(content_type) => {
+            format!(
+                "Der Server hat \"{}\" statt Videodaten geliefert.",
+                content_type
+            )
+        }
+        ChunkError::Length { expected, received } => format!(
+            "Nur {} von {} empfangen.",
+            format_size(*received),
+            format_size(*expected)
+        ),
+        ChunkError::Box(_) | ChunkError::MissingBox(_) => {
+            "Die empfangenen Daten sind kein gültiges MP4.".to_owned()
+        }
+    }
+}
+
+fn http_settings_error_message(e: &HttpSettingsError) -> String {
+    match e {
+        HttpSettingsError::Proxy(proxy) => format!("Ungültiger Proxy \"{}\".", proxy),
+        HttpSettingsError::Certificate(path) => format!(
+            "Aus \"{}\" konnten keine Zertifikate geladen werden.",
+            path.to_string_lossy()
+        ),
+        HttpSettingsError::UserAgent(user_agent) => {
+            format!("Ungültiger User-Agent \"{}\".", user_agent)
+        }
+        HttpSettingsError::Header(name) => format!("Ungültiger Header \"{}\".", name),
+    }
+}
+
+fn ffmpeg_error_message(e: &FfmpegCheckError) -> String {
+    match e {
+        FfmpegCheckError::NotExecutable(path) => {
+            format!("\"{}\" konnte nicht ausgeführt werden.", path)
+        }
+        FfmpegCheckError::UnknownVersion => "Unbekannte ffmpeg-Version.".to_owned(),
+        FfmpegCheckError::MissingFormats(formats) => {
+            format!("ffmpeg unterstützt nicht: {}.", formats.join(", "))
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct DownloadForm {
+    url: String,
+    quality: Quality,
+    dest_dir: Option<PathBuf>,
+    #[serde(default)]
+    segment_output: SegmentOutput,
+}
+
+impl Default for DownloadForm {
+    fn default() -> Self {
+        let video_dir = (|| {
+            let ud = UserDirs::new()?;
+            ud.video_dir()
+                .or(ud.download_dir())
+                .map(|p| p.to_path_buf())
+        })();
+
+        DownloadForm {
+            url: "".to_owned(),
+            quality: Quality::High,
+            dest_dir: video_dir,
+            segment_output: SegmentOutput::Combined,
+        }
+    }
+}
+
+impl DownloadForm {
+    fn reset(&mut self) {
+        self.url = "".to_owned();
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.url.is_empty() && self.dest_dir.is_some()
+    }
+}
+
+pub struct OondlApp {
+    download_form: DownloadForm,
+    maybe_clipboard: Option<Clipboard>,
+    open_file_dialog: Option<FileDialog>,
+    show_invalid_url: bool,
+    show_dest_dir_not_writeable: bool,
+    show_ffmpeg_missing: bool,
+    settings: Settings,
+    settings_window: Option<SettingsWindow>,
+    show_window: Option<ShowWindow>,
+    subscriptions_window: Option<SubscriptionsWindow>,
+    history_window: Option<HistoryWindow>,
+    segments_window: Option<SegmentsWindow>,
+    /// Request for a video that has been downloaded before, waiting for confirmation.
+    duplicate: Option<(DownloadRequest, HistoryEntry)>,
+    client: Client,
+    state: State,
+}
+
+impl OondlApp {
+    pub fn new(cc: &eframe::CreationContext<'_>, client: Client, settings: Settings) -> Self {
+        let download_form = if let Some(storage) = cc.storage {
+            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
+        } else {
+            DownloadForm::default()
+        };
+
+        Self {
+            download_form,
+            maybe_clipboard: Clipboard::new().ok(),
+            open_file_dialog: None,
+            show_invalid_url: false,
+            show_dest_dir_not_writeable: false,
+            show_ffmpeg_missing: false,
+            settings,
+            settings_window: None,
+            show_window: None,
+            subscriptions_window: None,
+            history_window: None,
+            segments_window: None,
+            duplicate: None,
+            client,
+            state: State::new(),
+        }
+    }
+
+    /// Adds the request, or asks for confirmation first if it was downloaded before.
+    fn add_download_unless_duplicate(&mut self, request: DownloadRequest) {
+        match self.client.find_request_in_history(&request) {
+            Some(entry) => self.duplicate = Some((request, entry)),
+            None => self.client.add_download(request, &mut self.state),
+        }
+    }
+}
+
+impl eframe::App for OondlApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        while let Some(u) = self.client.poll_update() {
+            if let StateUpdate::FfmpegChecked(Err(_)) = u {
+                self.show_ffmpeg_missing = true;
+            }
+            self.state.update(u);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.set_enabled(
+                !self.show_invalid_url
+                    && !self.show_dest_dir_not_writeable
+                    && !self.show_ffmpeg_missing
+                    && self.settings_window.is_none()
+                    && self.show_window.is_none()
+                    && self.subscriptions_window.is_none()
+                    && self.history_window.is_none()
+                    && self.segments_window.is_none()
+                    && self.duplicate.is_none()
+                    && self.state.verify_warning().is_none()
+                    && !self.state.has_error(),
+            );
+            ui.add_space(3.0);
+            egui::Grid::new("pos_size")
+                .num_columns(2)
+                .spacing([SPACE_4, SPACE_2])
+                .show(ui, |ui| {
+                    let url_label = ui.label("URL:");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("📋 Einfügen").clicked() {
+                            if let Some(cb) = &mut self.maybe_clipboard {
+                                if let Ok(text) = cb.get_text() {
+                                    self.download_form.url = text;
+                                }
+                            }
+                        }
+                        let te = egui::TextEdit::singleline(&mut self.download_form.url)
+                            .desired_width(f32::INFINITY)
+                            .hint_text(
+                                "z.B. https://on.orf.at/video/12345678 oder /profile/12345678",
+                            );
+                        ui.add_sized(ui.available_size(), te)
+                            .labelled_by(url_label.id);
+                    });
+                    ui.end_row();
+
+                    ui.label("Qualität:");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.download_form.quality, Quality::High, "Hoch");
+                        ui.radio_value(&mut self.download_form.quality, Quality::Medium, "Mittel");
+                        ui.radio_value(&mut self.download_form.quality, Quality::Low, "Niedrig");
+                    });
+                    ui.end_row();
+
+                    ui.label("Beiträge:")
+                        .on_hover_text("Bei Sendungen, die aus mehreren Beiträgen bestehen");
+                    segment_output_edit(ui, &mut self.download_form.segment_output);
+                    ui.end_row();
+
+                    ui.label("Zielordner:");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("📁 Auswählen").clicked() {
+                            let mut dialog =
+                                FileDialog::select_folder(self.download_form.dest_dir.clone())
+                                    .default_size(vec2(480.0, 350.0))
+                                    .resizable(false)
+                                    .anchor(Align2::CENTER_CENTER, Vec2::ZERO);
+                            dialog.open();
+                            self.open_file_dialog = Some(dialog);
+                        }
+
+                        let mut path_cow = Cow::Borrowed("");
+                        if let Some(p) = &self.download_form.dest_dir {
+                            path_cow = p.to_string_lossy();
+                        }
+
+                        uneditable_textedit(ui, path_cow.as_ref());
+                    });
+                    ui.end_row();
+                });
+
+            if let Some(dialog) = &mut self.open_file_dialog {
+                if dialog.show(ctx).selected() {
+                    if let Some(dir_path) = dialog.path() {
+                        self.download_form.dest_dir = Some(dir_path.to_path_buf());
+                    }
+                }
+            };
+
+            ui.add_space(SPACE_4);
+
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(self.download_form.is_valid(), |ui| {
+                    let download_clicked = ui.button("Download").clicked();
+                    let pick_clicked = ui
+                        .button("✂ Teile auswählen")
+                        .on_hover_text("Einzelne Beiträge einer Sendung herunterladen")
+                        .clicked();
+                    if download_clicked || pick_clicked {
+                        let url_res = VideoUrl::new(&self.download_form.url.trim());
+                        let show_url = ShowUrl::new(self.download_form.url.trim()).ok();
+                        let dest_dir_writeable =
+                            is_writable(self.download_form.dest_dir.as_ref().unwrap())
+                                .is_ok_and(|w| w);
+                        if self.state.ffmpeg_error().is_some() {
+                            self.show_ffmpeg_missing = true;
+                        } else if let (Some(show_url), true) = (&show_url, dest_dir_writeable) {
+                            self.state.take_show_listing();
+                            self.client.list_show(show_url.clone());
+                            self.show_window = Some(ShowWindow::new(show_url.clone()));
+                        } else if let (Ok(url), true, true) =
+                            (&url_res, dest_dir_writeable, pick_clicked)
+                        {
+                            self.state.take_segment_listing();
+                            self.client.list_segments(url.clone());
+                            self.segments_window = Some(SegmentsWindow::new());
+                            self.download_form.reset();
+                        } else if let (Ok(url), true) = (&url_res, dest_dir_writeable) {
+                            let mut request = DownloadRequest::new(
+                                url.clone(),
+                                self.download_form.quality,
+                                self.download_form.dest_dir.as_ref().unwrap().clone(),
+                            );
+                            request.segment_output = self.download_form.segment_output;
+                            self.add_download_unless_duplicate(request);
+                            self.download_form.reset();
+                        } else {
+                            self.show_invalid_url = url_res.is_err() && show_url.is_none();
+                            self.show_dest_dir_not_writeable = !dest_dir_writeable;
+                        }
+                    }
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("⚙ Einstellungen").clicked() {
+                        self.settings_window = Some(SettingsWindow::new(self.settings.clone()));
+                    }
+                    if ui.button("📜 Verlauf").clicked() {
+                        self.history_window = Some(HistoryWindow::new(self.client.history()));
+                    }
+                    if ui.button("📺 Abos").clicked() {
+                        self.subscriptions_window = Some(SubscriptionsWindow::new(
+                            self.settings.subscriptions.clone(),
+                            self.settings.subscription_interval,
+                            self.download_form.quality,
+                            self.download_form.dest_dir.clone(),
+                        ));
+                    }
+                });
+            });
+
+            ui.add_space(SPACE_4);
+
+            if self.state.phase() == Phase::Idle {
+                ui.vertical(|ui| {
+                    ui.set_height(123_f32);
+                    ui.centered_and_justified(|ui| {
+                        ui.label(RichText::new("Kein aktiver Download.").size(14.0));
+                    })
+                });
+            } else {
+                ui.group(|ui| {
+                    ui.set_width(ui.available_width());
+                    ui.add_space(SPACE_2);
+                    let title = self.state.title().unwrap_or("<Titel>");
+                    ui.label(RichText::new(title).strong().underline().size(14.0));
+                    ui.add_space(SPACE_2);
+                    match self.state.phase() {
+                        Phase::Analyzing => {
+                            ui.label("Analysieren");
+                            ui.add_space(SPACE);
+                            ui.spinner();
+                        }
+                        Phase::Downloading { progress, video_no } => {
+                            ui.label(format!(
+                                "Herunterladen {:.0}% Video {} von {}",
+                                progress.fraction() * 100_f32,
+                                video_no.0,
+                                video_no.1,
+                            ));
+                            let mut details = format!(
+                                "{} von {}",
+                                format_size(progress.downloaded),
+                                format_size(progress.total)
+                            );
+                            if progress.bytes_per_sec > 0 {
+                                details.push_str(&format!(
+                                    " – {}/s",
+                                    format_size(progress.bytes_per_sec)
+                                ));
+                            }
+                            if let Some(eta) = progress.eta() {
+                                details.push_str(&format!(" – noch {}", format_duration(eta)));
+                            }
+                            let pbar = egui::ProgressBar::new(progress.fraction()).text(details);
+                            ui.add_space(SPACE);
+                            ui.add(pbar);
+                        }
+                        Phase::Merging => {
+                            ui.label("Zusammenfügen");
+                            ui.add_space(SPACE);
+                            ui.spinner();
+                        }
+                        Phase::Idle => unreachable!(),
+                    }
+                    ui.add_space(SPACE_4);
+                    if ui.button("Abbrechen").clicked() {
+                        self.client.cancel_download();
+                    }
+                    ui.add_space(SPACE_2);
+                });
+            }
+
+            ui.add_space(SPACE_4);
+
+            ui.label(
+                RichText::new("Warteschlange")
+                    .underline()
+                    .size(14.0)
+                    .strong(),
+            );
+
+            if self.state.queue_is_empty() {
+                ui.centered_and_justified(|ui| {
+                    ui.label(RichText::new("Warteschlange ist leer.").size(14.0));
+                });
+            } else {
+                ui.add_space(SPACE_4);
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.state.retain_from_queue(|q| {
+                        let mut keep = true;
+
+                        ui.group(|ui| {
+                            ui.set_width(ui.available_width());
+                            ui.add_space(SPACE_2);
+                            ui.label(&q.title);
+                            if let Some(size) = q.estimated_size {
+                                ui.label(format!("ca. {}", format_size(size)));
+                            }
+                            if let Some(not_before) = q.not_before {
+                                ui.label(format!(
+                                    "Startet automatisch am {}",
+                                    not_before.format("%d.%m. um %H:%M Uhr")
+                                ));
+                            }
+                            ui.add_space(SPACE_4);
+                            if ui.button("Entfernen").clicked() {
+                                self.client.delete_download(q.request_id);
+                                keep = false;
+                            };
+                            ui.add_space(SPACE_2);
+                        });
+                        ui.add_space(SPACE_2);
+
+                        keep
+                    });
+                });
+            }
+        });
+
+        fn error_modal(ctx: &egui::Context, add_contents: impl FnOnce(&mut Ui)) {
+            egui::Window::new("Fehler!")
+                .collapsible(false)
+                .pivot(Align2::CENTER_TOP)
+                .fixed_pos(Pos2::new(300.0, 30.0))
+                .show(ctx, |ui| {
+                    ui.set_width(300.0);
+                    ui.add_space(SPACE_4);
+                    add_contents(ui);
+                    ui.add_space(SPACE);
+                });
+        }
+
+        if self.show_invalid_url || self.show_dest_dir_not_writeable {
+            error_modal(ctx, |ui| {
+                if self.show_invalid_url {
+                    ui.label(RichText::new("Keine gültige Url.").size(14.0));
+                }
+                if self.show_dest_dir_not_writeable {
+                    ui.label(RichText::new("Keine Schreibrechte für Zielordner.").size(14.0));
+                }
+                ui.add_space(SPACE_4);
+                if ui.button("OK").clicked() {
+                    self.show_invalid_url = false;
+                    self.show_dest_dir_not_writeable = false;
+                }
+            });
+        }
+
+        if self.show_ffmpeg_missing {
+            error_modal(ctx, |ui| {
+                ui.label(RichText::new("ffmpeg ist nicht verfügbar.").size(14.0));
+                if let Some(e) = self.state.ffmpeg_error() {
+                    ui.label(ffmpeg_error_message(e));
+                }
+                ui.label("Bitte ffmpeg installieren oder den Pfad in den Einstellungen angeben.");
+                ui.add_space(SPACE_4);
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() {
+                        self.show_ffmpeg_missing = false;
+                    }
+                    if ui.button("⚙ Einstellungen").clicked() {
+                        self.show_ffmpeg_missing = false;
+                        self.settings_window = Some(SettingsWindow::new(self.settings.clone()));
+                    }
+                });
+            });
+        }
+
+        if let Some(settings_window) = &mut self.settings_window {
+            match settings_window.show(ctx, &self.state) {
+                Some(SettingsAction::Save(settings)) => {
+                    self.client.update_settings((*settings).clone());
+                    self.settings = *settings;
+                    self.settings_window = None;
+                }
+                Some(SettingsAction::Cancel) => self.settings_window = None,
+                None => (),
+            }
+        }
+
+        if let Some(segments_window) = &mut self.segments_window {
+            if let Some(res) = self.state.take_segment_listing() {
+                segments_window.set_listing(res);
+            }
+            match segments_window.show(ctx, &mut self.download_form.segment_output) {
+                Some(SegmentsAction::Enqueue { url, segment_ids }) => {
+                    let mut request = DownloadRequest::new(
+                        url,
+                        self.download_form.quality,
+                        self.download_form.dest_dir.clone().unwrap(),
+                    );
+                    request.segment_output = self.download_form.segment_output;
+                    if !segment_ids.is_empty() {
+                        request.segment_ids = Some(segment_ids);
+                    }
+                    self.add_download_unless_duplicate(request);
+                    self.segments_window = None;
+                }
+                Some(SegmentsAction::Cancel) => self.segments_window = None,
+                None => (),
+            }
+        }
+
+        if let Some(history_window) = &mut self.history_window {
+            match history_window.show(ctx) {
+                Some(HistoryAction::Remove(entry)) => self.client.remove_from_history(&entry),
+                Some(HistoryAction::Close) => self.history_window = None,
+                None => (),
+            }
+        }
+
+        if let Some((request, entry)) = &self.duplicate {
+            let mut add = None;
+            egui::Window::new("Bereits heruntergeladen")
+                .collapsible(false)
+                .pivot(Align2::CENTER_TOP)
+                .fixed_pos(Pos2::new(300.0, 30.0))
+                .show(ctx, |ui| {
+                    ui.set_width(300.0);
+                    ui.add_space(SPACE_4);
+                    ui.label(
+                        RichText::new(format!(
+                            "Bereits am {} heruntergeladen:",
+                            entry.date.format("%d.%m.%Y um %H:%M Uhr")
+                        ))
+                        .size(14.0),
+                    );
+                    ui.label(&entry.title);
+                    if let Some(path) = &entry.path {
+                        ui.label(RichText::new(path.to_string_lossy()).weak());
+                    }
+                    ui.add_space(SPACE_4);
+                    ui.horizontal(|ui| {
+                        if ui.button("Trotzdem herunterladen").clicked() {
+                            add = Some(true);
+                        }
+                        if ui.button("Überspringen").clicked() {
+                            add = Some(false);
+                        }
+                    });
+                    ui.add_space(SPACE);
+                });
+            match add {
+                Some(true) => {
+                    let request = request.clone();
+                    self.client.add_download(request, &mut self.state);
+                    self.duplicate = None;
+                }
+                Some(false) => self.duplicate = None,
+                None => (),
+            }
+        }
+
+        if let Some(subscriptions_window) = &mut self.subscriptions_window {
+            match subscriptions_window.show(ctx) {
+                Some(SubscriptionsAction::Save {
+                    subscriptions,
+                    interval,
+                }) => {
+                    self.settings.subscriptions = subscriptions;
+                    self.settings.subscription_interval = interval;
+                    self.client.update_settings(self.settings.clone());
+                    self.subscriptions_window = None;
+                }
+                Some(SubscriptionsAction::Cancel) => self.subscriptions_window = None,
+                None => (),
+            }
+        }
+
+        if let Some(show_window) = &mut self.show_window {
+            if let Some(res) = self.state.take_show_listing() {
+                show_window.set_listing(res, |url| self.client.find_in_history(url).is_some());
+            }
+            match show_window.show(ctx) {
+                Some(ShowAction::Enqueue(urls)) => {
+                    for url in urls {
+                        let mut request = DownloadRequest::new(
+                            url,
+                            self.download_form.quality,
+                            self.download_form.dest_dir.as_ref().unwrap().clone(),
+                        );
+                        request.segment_output = self.download_form.segment_output;
+                        self.client.add_download(request, &mut self.state);
+                    }
+                    self.download_form.reset();
+                    self.show_window = None;
+                }
+                Some(ShowAction::Cancel) => self.show_window = None,
+                None => (),
+            }
+        }
+
+        if let Some(warning) = self.state.verify_warning() {
+            let mut dismissed = false;
+            egui::Window::new("Warnung")
+                .collapsible(false)
+                .pivot(Align2::CENTER_TOP)
+                .fixed_pos(Pos2::new(300.0, 30.0))
+                .show(ctx, |ui| {
+                    ui.set_width(300.0);
+                    ui.add_space(SPACE_4);
+                    ui.label(
+                        RichText::new(format!(
+                            "„{}“ wurde gespeichert, ist aber möglicherweise unvollständig.",
+                            warning.title
+                        ))
+                        .size(14.0),
+                    );
+                    ui.add_space(SPACE_2);
+                    ui.label(verify_error_message(&warning.error));
+                    ui.label(RichText::new(warning.path.to_string_lossy()).weak());
+                    ui.add_space(SPACE_4);
+                    dismissed = ui.button("OK").clicked();
+                    ui.add_space(SPACE);
+                });
+            if dismissed {
+                self.state.dismiss_verify_warning();
+            }
+        }
+
+        if self.state.has_error() {
+            error_modal(ctx, |ui| {
+                let err_message = error_message(self.state.error().unwrap());
+                ui.label(RichText::new(err_message).size(14.0));
+                ui.add_space(SPACE_4);
+                if let Some(report) = error_report(&self.state) {
+                    egui::CollapsingHeader::new("Details").show(ui, |ui| {
+                        egui::ScrollArea::vertical()
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut report.as_str())
+                                        .font(egui::TextStyle::Monospace)
+                                        .desired_width(f32::INFINITY),
+                                );
+                            });
+                        ui.add_space(SPACE);
+                        let copy_button = ui.add_enabled(
+                            self.maybe_clipboard.is_some(),
+                            egui::Button::new("📋 Kopieren"),
+                        );
+                        if copy_button.clicked() {
+                            if let Some(cb) = &mut self.maybe_clipboard {
+                                if let Err(e) = cb.set_text(report) {
+                                    log::warn!("could not copy error report: {}", e);
+                                }
+                            }
+                        }
+                    });
+                    ui.add_space(SPACE_4);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Abbrechen").clicked() {
+                        self.client.cancel_on_error();
+                    }
+                    if let Some(Error::YouthProtectionError { window }) = self.state.error() {
+                        let start = window.start.format("%H:%M");
+                        if ui
+                            .button(format!("⏰ Ab {} Uhr automatisch starten", start))
+                            .clicked()
+                        {
+                            self.client.defer(window.next_start(Local::now()));
+                        }
+                    } else if ui.button("Wiederholen").clicked() {
+                        self.client.retry();
+                    }
+                });
+            });
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.download_form);
+        eframe::set_value(storage, SETTINGS_KEY, &self.settings);
+    }
+
+    fn persist_egui_memory(&self) -> bool {
+        false
+    }
+
+    fn on_exit(&mut self, _gl: Option<&Context>) {
+        self.client.shutdown();
+        log::debug!("gui exited");
+    }
+}
@@ -0,0 +1,156 @@
+use egui::{Align2, Pos2, RichText};
+
+use super::{error_message, format_duration, segment_output_edit, SPACE, SPACE_2, SPACE_4};
+use crate::downloader::{EpisodeSegments, Error, SegmentOutput, VideoUrl};
+
+pub enum SegmentsAction {
+    /// Download the segments with the ids, all of them if empty.
+    Enqueue {
+        url: VideoUrl,
+        segment_ids: Vec<String>,
+    },
+    Cancel,
+}
+
+enum Listing {
+    Loading,
+    Loaded {
+        episode: EpisodeSegments,
+        selected: Vec<bool>,
+    },
+    Failed(Error),
+}
+
+/// Lists the segments of an episode to pick those to download.
+pub struct SegmentsWindow {
+    listing: Listing,
+}
+
+impl SegmentsWindow {
+    pub fn new() -> Self {
+        Self {
+            listing: Listing::Loading,
+        }
+    }
+
+    pub fn set_listing(&mut self, res: Result<EpisodeSegments, Error>) {
+        self.listing = match res {
+            Ok(episode) => Listing::Loaded {
+                selected: vec![true; episode.segments.len()],
+                episode,
+            },
+            Err(e) => Listing::Failed(e),
+        };
+    }
+
+    /// Shows the window, `output` is the choice how to save the segments.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        output: &mut SegmentOutput,
+    ) -> Option<SegmentsAction> {
+        let mut action = None;
+
+        egui::Window::new("Teile auswählen")
+            .collapsible(false)
+            .resizable(false)
+            .pivot(Align2::CENTER_TOP)
+            .fixed_pos(Pos2::new(300.0, 30.0))
+            .show(ctx, |ui| {
+                ui.set_width(450.0);
+                ui.add_space(SPACE_2);
+
+                match &mut self.listing {
+                    Listing::Loading => {
+                        ui.horizontal(|ui| {
+                            ui.label("Analysieren");
+                            ui.add_space(SPACE);
+                            ui.spinner();
+                        });
+                    }
+                    Listing::Failed(e) => {
+                        ui.label(error_message(e));
+                    }
+                    Listing::Loaded { episode, .. } if episode.segments.is_empty() => {
+                        ui.label(RichText::new(&episode.title).strong());
+                        ui.add_space(SPACE_2);
+                        ui.label("Diese Sendung ist nicht in Teile gegliedert.");
+                    }
+                    Listing::Loaded { episode, selected } => {
+                        ui.label(RichText::new(&episode.title).strong());
+                        ui.add_space(SPACE_2);
+                        ui.horizontal(|ui| {
+                            if ui.button("Alle auswählen").clicked() {
+                                selected.iter_mut().for_each(|s| *s = true);
+                            }
+                            if ui.button("Keine").clicked() {
+                                selected.iter_mut().for_each(|s| *s = false);
+                            }
+                        });
+                        ui.add_space(SPACE_2);
+                        egui::ScrollArea::vertical()
+                            .max_height(300.0)
+                            .show(ui, |ui| {
+                                ui.set_width(ui.available_width());
+                                for (idx, (segment, s)) in
+                                    episode.segments.iter().zip(selected.iter_mut()).enumerate()
+                                {
+                                    let mut label = format!(
+                                        "{}. {}",
+                                        idx + 1,
+                                        segment.title.as_deref().unwrap_or(&segment.id)
+                                    );
+                                    if let Some(duration) = segment.duration {
+                                        label
+                                            .push_str(&format!(" ({})", format_duration(duration)));
+                                    }
+                                    ui.checkbox(s, label);
+                                }
+                            });
+                        ui.add_space(SPACE_4);
+                        ui.horizontal(|ui| {
+                            ui.label("Speichern als:");
+                            segment_output_edit(ui, output);
+                        });
+                    }
+                }
+
+                ui.add_space(SPACE_4);
+                ui.horizontal(|ui| {
+                    if let Listing::Loaded { episode, selected } = &self.listing {
+                        let segment_ids: Vec<String> = episode
+                            .segments
+                            .iter()
+                            .zip(selected)
+                            .filter(|(_, s)| **s)
+                            .map(|(segment, _)| segment.id.clone())
+                            .collect();
+                        let label = if episode.segments.is_empty() {
+                            "Herunterladen".to_owned()
+                        } else {
+                            format!("Ausgewählte herunterladen ({})", segment_ids.len())
+                        };
+                        if ui
+                            .add_enabled(
+                                episode.segments.is_empty() || !segment_ids.is_empty(),
+                                egui::Button::new(label),
+                            )
+                            .clicked()
+                        {
+                            let all = segment_ids.len() == episode.segments.len();
+                            action = Some(SegmentsAction::Enqueue {
+                                url: episode.url.clone(),
+                                segment_ids: if all { vec![] } else { segment_ids },
+                            });
+                        }
+                    }
+                    if ui.button("Abbrechen").clicked() {
+                        action = Some(SegmentsAction::Cancel);
+                    }
+                });
+                ui.add_space(SPACE_2);
+            });
+
+        action
+    }
+}
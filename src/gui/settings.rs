@@ -0,0 +1,422 @@
+use chrono::{Local, NaiveTime, Timelike};
+use egui::{vec2, Align2, Pos2, Vec2};
+use egui_file::FileDialog;
+
+use super::{
+    ffmpeg_error_message, http_settings_error_message, uneditable_textedit, SPACE, SPACE_2, SPACE_4,
+};
+use crate::downloader::{
+    check_http_settings, project_dirs, HttpSettingsError, ProxyMode, RateLimitRule, Settings, State,
+};
+use crate::logging::export_diagnostics;
+
+const DEFAULT_RATE_LIMIT: u64 = 2_000_000;
+
+/// Edits a rate limit in MB/s, unchecking the box removes the limit.
+fn rate_limit_edit(ui: &mut egui::Ui, limit: &mut Option<u64>, label: &str) {
+    let mut limited = limit.is_some();
+    if ui.checkbox(&mut limited, label).changed() {
+        *limit = limited.then_some(DEFAULT_RATE_LIMIT);
+    }
+    if let Some(bytes_per_sec) = limit {
+        let mut mb_per_sec = *bytes_per_sec as f64 / 1_000_000.0;
+        ui.add(
+            egui::DragValue::new(&mut mb_per_sec)
+                .speed(0.1)
+                .clamp_range(0.1..=1000.0)
+                .fixed_decimals(1)
+                .suffix(" MB/s"),
+        );
+        *bytes_per_sec = (mb_per_sec * 1_000_000.0) as u64;
+    }
+}
+
+fn time_edit(ui: &mut egui::Ui, time: &mut NaiveTime) {
+    let mut hour = time.hour();
+    let mut minute = time.minute();
+    ui.add(
+        egui::DragValue::new(&mut hour)
+            .clamp_range(0..=23)
+            .custom_formatter(|n, _| format!("{:02}", n)),
+    );
+    ui.label(":");
+    ui.add(
+        egui::DragValue::new(&mut minute)
+            .clamp_range(0..=59)
+            .custom_formatter(|n, _| format!("{:02}", n)),
+    );
+    if let Some(t) = NaiveTime::from_hms_opt(hour, minute, 0) {
+        *time = t;
+    }
+}
+
+pub enum SettingsAction {
+    Save(Box<Settings>),
+    Cancel,
+}
+
+enum DialogTarget {
+    Ffmpeg,
+    TempDir,
+    CaCertificate,
+    Diagnostics,
+}
+
+pub struct SettingsWindow {
+    settings: Settings,
+    open_file_dialog: Option<(DialogTarget, FileDialog)>,
+    error: Option<HttpSettingsError>,
+    /// Result of the last diagnostics export, to be shown next to the button.
+    export_status: Option<String>,
+}
+
+impl SettingsWindow {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            open_file_dialog: None,
+            error: None,
+            export_status: None,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &State) -> Option<SettingsAction> {
+        let mut action = None;
+
+        egui::Window::new("Einstellungen")
+            .collapsible(false)
+            .resizable(false)
+            .pivot(Align2::CENTER_TOP)
+            .fixed_pos(Pos2::new(300.0, 30.0))
+            .show(ctx, |ui| {
+                ui.set_width(450.0);
+                ui.add_space(SPACE_2);
+                egui::Grid::new("settings")
+                    .num_columns(2)
+                    .spacing([SPACE_4, SPACE_2])
+                    .show(ui, |ui| {
+                        let ffmpeg_label = ui.label("ffmpeg:");
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("📁 Auswählen").clicked() {
+                                self.open_dialog(DialogTarget::Ffmpeg, FileDialog::open_file(None));
+                            }
+                            let te = egui::TextEdit::singleline(&mut self.settings.ffmpeg_path)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("ffmpeg");
+                            ui.add_sized(ui.available_size(), te)
+                                .labelled_by(ffmpeg_label.id);
+                        });
+                        ui.end_row();
+
+                        ui.label("");
+                        if let Some(version) = state.ffmpeg_version() {
+                            ui.label(format!("✔ ffmpeg {}", version));
+                        } else if let Some(e) = state.ffmpeg_error() {
+                            ui.label(format!("✖ {}", ffmpeg_error_message(e)));
+                        } else {
+                            ui.spinner();
+                        }
+                        ui.end_row();
+
+                        ui.label("Temporärer Ordner:");
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui
+                                .button("↺")
+                                .on_hover_text("Zielordner verwenden")
+                                .clicked()
+                            {
+                                self.settings.temp_dir = None;
+                            }
+                            if let Some(cache_dir) =
+                                project_dirs().map(|d| d.cache_dir().to_owned())
+                            {
+                                if ui
+                                    .button("Cache")
+                                    .on_hover_text("Cache-Ordner verwenden")
+                                    .clicked()
+                                {
+                                    self.settings.temp_dir = Some(cache_dir);
+                                }
+                            }
+                            if ui.button("📁 Auswählen").clicked() {
+                                let dialog =
+                                    FileDialog::select_folder(self.settings.temp_dir.clone());
+                                self.open_dialog(DialogTarget::TempDir, dialog);
+                            }
+                            match &self.settings.temp_dir {
+                                Some(p) => uneditable_textedit(ui, &p.to_string_lossy()),
+                                None => uneditable_textedit(ui, "Zielordner"),
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Parallele Downloads:");
+                        ui.add(egui::Slider::new(
+                            &mut self.settings.concurrent_chunks,
+                            1..=16,
+                        ));
+                        ui.end_row();
+
+                        #[cfg(target_os = "linux")]
+                        {
+                            ui.label("Zusammenfügen:");
+                            ui.checkbox(
+                                &mut self.settings.pipelined,
+                                "Während des Herunterladens (halber Speicherbedarf)",
+                            );
+                            ui.end_row();
+                        }
+
+                        ui.label("Bandbreite:");
+                        ui.horizontal(|ui| {
+                            rate_limit_edit(ui, &mut self.settings.rate_limit, "Begrenzen auf");
+                        });
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.label("Abweichend zu bestimmten Uhrzeiten:");
+                        ui.end_row();
+
+                        let mut delete = None;
+                        for (idx, rule) in self.settings.rate_limit_schedule.iter_mut().enumerate()
+                        {
+                            ui.label("");
+                            ui.horizontal(|ui| {
+                                ui.label("von");
+                                time_edit(ui, &mut rule.start);
+                                ui.label("bis");
+                                time_edit(ui, &mut rule.end);
+                                ui.add_space(SPACE_2);
+                                rate_limit_edit(ui, &mut rule.limit, "");
+                                if rule.limit.is_none() {
+                                    ui.label("unbegrenzt");
+                                }
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        if ui.button("🗑").on_hover_text("Entfernen").clicked() {
+                                            delete = Some(idx);
+                                        }
+                                    },
+                                );
+                            });
+                            ui.end_row();
+                        }
+                        if let Some(idx) = delete {
+                            self.settings.rate_limit_schedule.remove(idx);
+                        }
+
+                        ui.label("");
+                        if ui.button("+ Zeitraum").clicked() {
+                            self.settings.rate_limit_schedule.push(RateLimitRule {
+                                start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                                end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                                limit: Some(DEFAULT_RATE_LIMIT),
+                            });
+                        }
+                        ui.end_row();
+
+                        ui.label("Diagnose:");
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button("📦 Exportieren")
+                                .on_hover_text(
+                                    "Protokolle und Einstellungen als ZIP-Datei speichern",
+                                )
+                                .clicked()
+                            {
+                                let file_name = format!(
+                                    "oondl-diagnose-{}.zip",
+                                    Local::now().format("%Y%m%d-%H%M%S")
+                                );
+                                let dialog =
+                                    FileDialog::save_file(None).default_filename(file_name);
+                                self.open_dialog(DialogTarget::Diagnostics, dialog);
+                            }
+                            if let Some(status) = &self.export_status {
+                                ui.label(status);
+                            }
+                        });
+                        ui.end_row();
+                    });
+
+                ui.add_space(SPACE_2);
+                egui::CollapsingHeader::new("Netzwerk").show(ui, |ui| self.network_settings(ui));
+
+                if let Some((target, dialog)) = &mut self.open_file_dialog {
+                    if dialog.show(ctx).selected() {
+                        if let Some(path) = dialog.path() {
+                            match target {
+                                DialogTarget::Ffmpeg => {
+                                    self.settings.ffmpeg_path = path.to_string_lossy().into_owned();
+                                }
+                                DialogTarget::TempDir => {
+                                    self.settings.temp_dir = Some(path.to_path_buf());
+                                }
+                                DialogTarget::CaCertificate => {
+                                    self.settings.ca_certificates.push(path.to_path_buf());
+                                }
+                                DialogTarget::Diagnostics => {
+                                    let res = export_diagnostics(
+                                        path,
+                                        &self.settings,
+                                        state.ffmpeg_version(),
+                                    );
+                                    self.export_status = Some(match res {
+                                        Ok(()) => "✔ Gespeichert".to_owned(),
+                                        Err(e) => {
+                                            log::error!("could not export diagnostics: {:#}", e);
+                                            "✖ Speichern fehlgeschlagen".to_owned()
+                                        }
+                                    });
+                                }
+                            }
+                        }
+                    }
+                };
+
+                if let Some(e) = &self.error {
+                    ui.add_space(SPACE_2);
+                    ui.colored_label(ui.visuals().error_fg_color, http_settings_error_message(e));
+                }
+
+                ui.add_space(SPACE_4);
+                ui.horizontal(|ui| {
+                    if ui.button("Speichern").clicked() {
+                        match check_http_settings(&self.settings) {
+                            Ok(()) => {
+                                action = Some(SettingsAction::Save(Box::new(self.settings.clone())))
+                            }
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                    if ui.button("Abbrechen").clicked() {
+                        action = Some(SettingsAction::Cancel);
+                    }
+                });
+                ui.add_space(SPACE);
+            });
+
+        action
+    }
+
+    fn network_settings(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("network_settings")
+            .num_columns(2)
+            .spacing([SPACE_4, SPACE_2])
+            .show(ui, |ui| {
+                ui.label("Proxy:");
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.settings.proxy_mode,
+                        ProxyMode::Environment,
+                        "System",
+                    )
+                    .on_hover_text("Aus HTTP_PROXY, HTTPS_PROXY und ALL_PROXY");
+                    ui.radio_value(&mut self.settings.proxy_mode, ProxyMode::Disabled, "Keiner");
+                    ui.radio_value(&mut self.settings.proxy_mode, ProxyMode::Custom, "Manuell");
+                });
+                ui.end_row();
+
+                if self.settings.proxy_mode == ProxyMode::Custom {
+                    ui.label("");
+                    let te = egui::TextEdit::singleline(&mut self.settings.proxy_url)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("http://proxy:8080 oder socks5://proxy:1080");
+                    ui.add(te);
+                    ui.end_row();
+                }
+
+                ui.label("CA-Zertifikate:");
+                if ui.button("+ Hinzufügen").clicked() {
+                    let dialog = FileDialog::open_file(None).show_files_filter(Box::new(|p| {
+                        p.extension().is_some_and(|e| e == "pem" || e == "crt")
+                    }));
+                    self.open_dialog(DialogTarget::CaCertificate, dialog);
+                }
+                ui.end_row();
+
+                let mut delete = None;
+                for (idx, path) in self.settings.ca_certificates.iter().enumerate() {
+                    ui.label("");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("🗑").on_hover_text("Entfernen").clicked() {
+                            delete = Some(idx);
+                        }
+                        uneditable_textedit(ui, &path.to_string_lossy());
+                    });
+                    ui.end_row();
+                }
+                if let Some(idx) = delete {
+                    self.settings.ca_certificates.remove(idx);
+                }
+
+                ui.label("User-Agent:");
+                let te = egui::TextEdit::singleline(&mut self.settings.user_agent)
+                    .desired_width(f32::INFINITY)
+                    .hint_text("Keiner");
+                ui.add(te);
+                ui.end_row();
+
+                ui.label("Header:");
+                if ui.button("+ Hinzufügen").clicked() {
+                    self.settings.headers.push((String::new(), String::new()));
+                }
+                ui.end_row();
+
+                let mut delete = None;
+                for (idx, (name, value)) in self.settings.headers.iter_mut().enumerate() {
+                    ui.label("");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(name)
+                                .desired_width(120.0)
+                                .hint_text("Name"),
+                        );
+                        ui.label(":");
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("🗑").on_hover_text("Entfernen").clicked() {
+                                delete = Some(idx);
+                            }
+                            ui.add(
+                                egui::TextEdit::singleline(value)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("Wert"),
+                            );
+                        });
+                    });
+                    ui.end_row();
+                }
+                if let Some(idx) = delete {
+                    self.settings.headers.remove(idx);
+                }
+
+                ui.label("Timeouts:");
+                ui.horizontal(|ui| {
+                    ui.label("Verbindung");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.connect_timeout)
+                            .clamp_range(1..=300)
+                            .suffix(" s"),
+                    );
+                    ui.add_space(SPACE_2);
+                    ui.label("Lesen");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.read_timeout)
+                            .clamp_range(1..=300)
+                            .suffix(" s"),
+                    );
+                });
+                ui.end_row();
+            });
+    }
+
+    fn open_dialog(&mut self, target: DialogTarget, dialog: FileDialog) {
+        let mut dialog = dialog
+            .default_size(vec2(480.0, 350.0))
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO);
+        dialog.open();
+        self.open_file_dialog = Some((target, dialog));
+    }
+}
@@ -0,0 +1,182 @@
+use egui::{Align2, Pos2, RichText};
+
+use super::{error_message, format_duration, SPACE, SPACE_2, SPACE_4};
+use crate::downloader::{Error, ShowEpisode, ShowUrl, VideoUrl};
+
+pub enum ShowAction {
+    Enqueue(Vec<VideoUrl>),
+    Cancel,
+}
+
+struct Item {
+    episode: ShowEpisode,
+    selected: bool,
+    /// Whether the episode is in the download history.
+    downloaded: bool,
+}
+
+enum Listing {
+    Loading,
+    Loaded(Vec<Item>),
+    Failed(Error),
+}
+
+/// Lists the episodes of a show to pick those to download.
+pub struct ShowWindow {
+    url: ShowUrl,
+    listing: Listing,
+    filter: String,
+}
+
+impl ShowWindow {
+    pub fn new(url: ShowUrl) -> Self {
+        Self {
+            url,
+            listing: Listing::Loading,
+            filter: "".to_owned(),
+        }
+    }
+
+    /// Shows the listed episodes, those already downloaded are not selected.
+    pub fn set_listing(
+        &mut self,
+        res: Result<Vec<ShowEpisode>, Error>,
+        is_downloaded: impl Fn(&VideoUrl) -> bool,
+    ) {
+        self.listing = match res {
+            Ok(episodes) => Listing::Loaded(
+                episodes
+                    .into_iter()
+                    .map(|episode| {
+                        let downloaded = is_downloaded(&episode.url);
+                        Item {
+                            episode,
+                            selected: !downloaded,
+                            downloaded,
+                        }
+                    })
+                    .collect(),
+            ),
+            Err(e) => Listing::Failed(e),
+        };
+    }
+
+    fn matches(filter: &str, episode: &ShowEpisode) -> bool {
+        episode
+            .title
+            .to_lowercase()
+            .contains(&filter.trim().to_lowercase())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ShowAction> {
+        let mut action = None;
+
+        egui::Window::new("Sendung")
+            .collapsible(false)
+            .resizable(false)
+            .pivot(Align2::CENTER_TOP)
+            .fixed_pos(Pos2::new(300.0, 30.0))
+            .show(ctx, |ui| {
+                ui.set_width(450.0);
+                ui.add_space(SPACE_2);
+                ui.label(self.url.as_str());
+                ui.add_space(SPACE_4);
+
+                match &mut self.listing {
+                    Listing::Loading => {
+                        ui.horizontal(|ui| {
+                            ui.label("Folgen werden geladen");
+                            ui.add_space(SPACE);
+                            ui.spinner();
+                        });
+                    }
+                    Listing::Failed(e) => {
+                        ui.label(error_message(e));
+                    }
+                    Listing::Loaded(episodes) if episodes.is_empty() => {
+                        ui.label("Derzeit sind keine Folgen verfügbar.");
+                    }
+                    Listing::Loaded(episodes) => {
+                        let filter = &mut self.filter;
+                        ui.horizontal(|ui| {
+                            let filter_label = ui.label("Filter:");
+                            ui.add(
+                                egui::TextEdit::singleline(filter)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("Titel enthält …"),
+                            )
+                            .labelled_by(filter_label.id);
+                        });
+                        ui.add_space(SPACE_2);
+                        ui.horizontal(|ui| {
+                            for (label, selected) in [("Alle auswählen", true), ("Keine", false)] {
+                                if ui.button(label).clicked() {
+                                    for item in episodes.iter_mut() {
+                                        if Self::matches(filter, &item.episode) {
+                                            item.selected = selected;
+                                        }
+                                    }
+                                }
+                            }
+                        });
+                        ui.add_space(SPACE_2);
+                        egui::ScrollArea::vertical()
+                            .max_height(300.0)
+                            .show(ui, |ui| {
+                                ui.set_width(ui.available_width());
+                                for Item {
+                                    episode,
+                                    selected,
+                                    downloaded,
+                                } in episodes.iter_mut()
+                                {
+                                    if !Self::matches(filter, episode) {
+                                        continue;
+                                    }
+                                    let mut details = vec![];
+                                    if let Some(date) = episode.date {
+                                        details.push(date.format("%d.%m.%Y %H:%M").to_string());
+                                    }
+                                    if let Some(duration) = episode.duration {
+                                        details.push(format_duration(duration));
+                                    }
+                                    if *downloaded {
+                                        details.push("✔ bereits heruntergeladen".to_owned());
+                                    }
+                                    ui.checkbox(selected, &episode.title);
+                                    if !details.is_empty() {
+                                        ui.indent(episode.url.video_id(), |ui| {
+                                            ui.label(RichText::new(details.join(" – ")).weak());
+                                        });
+                                    }
+                                }
+                            });
+                    }
+                }
+
+                ui.add_space(SPACE_4);
+                ui.horizontal(|ui| {
+                    if let Listing::Loaded(episodes) = &self.listing {
+                        let urls: Vec<VideoUrl> = episodes
+                            .iter()
+                            .filter(|i| i.selected && Self::matches(&self.filter, &i.episode))
+                            .map(|i| i.episode.url.clone())
+                            .collect();
+                        let label = format!("Ausgewählte herunterladen ({})", urls.len());
+                        if ui
+                            .add_enabled(!urls.is_empty(), egui::Button::new(label))
+                            .clicked()
+                        {
+                            action = Some(ShowAction::Enqueue(urls));
+                        }
+                    }
+                    if ui.button("Abbrechen").clicked() {
+                        action = Some(ShowAction::Cancel);
+                    }
+                });
+                ui.add_space(SPACE_2);
+            });
+
+        action
+    }
+}
@@ -0,0 +1,252 @@
+use std::path::PathBuf;
+
+use egui::{vec2, Align2, Pos2, Vec2};
+use egui_file::FileDialog;
+
+use super::{uneditable_textedit, SPACE, SPACE_2, SPACE_4};
+use crate::downloader::{Quality, Region, ShowUrl, Subscription};
+
+pub enum SubscriptionsAction {
+    Save {
+        subscriptions: Vec<Subscription>,
+        interval: u64,
+    },
+    Cancel,
+}
+
+/// Edits the subscriptions to shows. New ones start with the quality and the destination of
+/// the download form.
+pub struct SubscriptionsWindow {
+    subscriptions: Vec<Subscription>,
+    interval: u64,
+    new_url: String,
+    quality: Quality,
+    dest_dir: Option<PathBuf>,
+    /// Index of the subscription whose destination is being selected.
+    open_file_dialog: Option<(usize, FileDialog)>,
+    error: Option<String>,
+}
+
+impl SubscriptionsWindow {
+    pub fn new(
+        subscriptions: Vec<Subscription>,
+        interval: u64,
+        quality: Quality,
+        dest_dir: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            subscriptions,
+            interval,
+            new_url: String::new(),
+            quality,
+            dest_dir,
+            open_file_dialog: None,
+            error: None,
+        }
+    }
+
+    fn add(&mut self) {
+        let Ok(url) = ShowUrl::new(self.new_url.trim()) else {
+            self.error = Some("Keine gültige URL einer Sendung.".to_owned());
+            return;
+        };
+        let Some(dest_dir) = self.dest_dir.clone() else {
+            self.error = Some("Bitte im Hauptfenster einen Zielordner auswählen.".to_owned());
+            return;
+        };
+        self.subscriptions
+            .push(Subscription::new(&url, self.quality, dest_dir));
+        self.new_url.clear();
+        self.error = None;
+    }
+
+    /// Checks the title filters, returning a message for the first invalid one.
+    fn validate(&self) -> Result<(), String> {
+        for s in &self.subscriptions {
+            if let Err(e) = s.filter() {
+                log::debug!("invalid title filter {:?}: {}", s.title_filter, e);
+                return Err(format!("Ungültiger Titelfilter: {}", s.title_filter));
+            }
+        }
+        Ok(())
+    }
+
+    fn subscription_edit(
+        ui: &mut egui::Ui,
+        idx: usize,
+        s: &mut Subscription,
+        open_file_dialog: &mut Option<(usize, FileDialog)>,
+    ) {
+        egui::Grid::new(("subscription", idx))
+            .num_columns(2)
+            .spacing([SPACE_4, SPACE_2])
+            .show(ui, |ui| {
+                ui.label("Titel:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut s.title_filter)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("Regulärer Ausdruck, z.B. ^ZIB 1"),
+                );
+                ui.end_row();
+
+                ui.label("Bundesland:");
+                egui::ComboBox::from_id_source(("region", idx))
+                    .selected_text(s.region.map_or("Alle", Region::name))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut s.region, None, "Alle");
+                        for region in Region::ALL {
+                            ui.selectable_value(&mut s.region, Some(region), region.name());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Mindestdauer:");
+                ui.horizontal(|ui| {
+                    let mut limited = s.min_duration.is_some();
+                    if ui.checkbox(&mut limited, "").changed() {
+                        s.min_duration = limited.then_some(10);
+                    }
+                    if let Some(minutes) = &mut s.min_duration {
+                        ui.add(
+                            egui::DragValue::new(minutes)
+                                .clamp_range(1..=600)
+                                .suffix(" min"),
+                        );
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Qualität:");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut s.quality, Quality::High, "Hoch");
+                    ui.radio_value(&mut s.quality, Quality::Medium, "Mittel");
+                    ui.radio_value(&mut s.quality, Quality::Low, "Niedrig");
+                });
+                ui.end_row();
+
+                ui.label("Zielordner:");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("📁 Auswählen").clicked() {
+                        let mut dialog = FileDialog::select_folder(Some(s.dest_dir.clone()))
+                            .default_size(vec2(480.0, 350.0))
+                            .resizable(false)
+                            .anchor(Align2::CENTER_CENTER, Vec2::ZERO);
+                        dialog.open();
+                        *open_file_dialog = Some((idx, dialog));
+                    }
+                    uneditable_textedit(ui, &s.dest_dir.to_string_lossy());
+                });
+                ui.end_row();
+            });
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<SubscriptionsAction> {
+        let mut action = None;
+
+        egui::Window::new("Abos")
+            .collapsible(false)
+            .resizable(false)
+            .pivot(Align2::CENTER_TOP)
+            .fixed_pos(Pos2::new(300.0, 30.0))
+            .show(ctx, |ui| {
+                ui.set_width(450.0);
+                ui.add_space(SPACE_2);
+                ui.horizontal(|ui| {
+                    let url_label = ui.label("Sendung:");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("+ Abonnieren").clicked() {
+                            self.add();
+                        }
+                        let te = egui::TextEdit::singleline(&mut self.new_url)
+                            .desired_width(f32::INFINITY)
+                            .hint_text("z.B. https://on.orf.at/profile/12345678");
+                        ui.add_sized(ui.available_size(), te)
+                            .labelled_by(url_label.id);
+                    });
+                });
+                ui.add_space(SPACE_2);
+                ui.horizontal(|ui| {
+                    ui.label("Auf neue Folgen prüfen alle");
+                    ui.add(
+                        egui::DragValue::new(&mut self.interval)
+                            .clamp_range(5..=1440)
+                            .suffix(" min"),
+                    );
+                });
+                ui.add_space(SPACE_4);
+
+                if self.subscriptions.is_empty() {
+                    ui.label("Keine Abos.");
+                } else {
+                    let mut delete = None;
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            for (idx, s) in self.subscriptions.iter_mut().enumerate() {
+                                ui.group(|ui| {
+                                    ui.set_width(ui.available_width());
+                                    ui.horizontal(|ui| {
+                                        ui.checkbox(&mut s.enabled, "");
+                                        ui.label(&s.url);
+                                        ui.with_layout(
+                                            egui::Layout::right_to_left(egui::Align::Center),
+                                            |ui| {
+                                                if ui
+                                                    .button("🗑")
+                                                    .on_hover_text("Abo beenden")
+                                                    .clicked()
+                                                {
+                                                    delete = Some(idx);
+                                                }
+                                            },
+                                        );
+                                    });
+                                    ui.add_space(SPACE_2);
+                                    Self::subscription_edit(ui, idx, s, &mut self.open_file_dialog);
+                                });
+                                ui.add_space(SPACE_2);
+                            }
+                        });
+                    if let Some(idx) = delete {
+                        self.subscriptions.remove(idx);
+                    }
+                }
+
+                if let Some((idx, dialog)) = &mut self.open_file_dialog {
+                    if dialog.show(ctx).selected() {
+                        if let (Some(path), Some(s)) =
+                            (dialog.path(), self.subscriptions.get_mut(*idx))
+                        {
+                            s.dest_dir = path.to_path_buf();
+                        }
+                    }
+                }
+
+                if let Some(e) = &self.error {
+                    ui.add_space(SPACE_2);
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+
+                ui.add_space(SPACE_4);
+                ui.horizontal(|ui| {
+                    if ui.button("Speichern").clicked() {
+                        match self.validate() {
+                            Ok(()) => {
+                                action = Some(SubscriptionsAction::Save {
+                                    subscriptions: self.subscriptions.clone(),
+                                    interval: self.interval,
+                                })
+                            }
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                    if ui.button("Abbrechen").clicked() {
+                        action = Some(SubscriptionsAction::Cancel);
+                    }
+                });
+                ui.add_space(SPACE);
+            });
+
+        action
+    }
+}
@@ -0,0 +1,209 @@
+//! Logging to stderr, configured by `RUST_LOG`, and to rotating files in the data directory.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::downloader::{project_dirs, Settings};
+
+const LOG_FILE_NAME: &str = "oondl.log";
+const MAX_LOG_FILE_SIZE: u64 = 2_000_000;
+/// Number of rotated files kept besides the current one.
+const ROTATED_LOG_FILES: usize = 2;
+
+pub fn log_dir() -> Option<PathBuf> {
+    project_dirs().map(|d| d.data_dir().join("logs"))
+}
+
+/// Log files ordered from oldest to newest.
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    (1..=ROTATED_LOG_FILES)
+        .rev()
+        .map(|n| rotated_path(dir, n))
+        .chain([dir.join(LOG_FILE_NAME)])
+        .filter(|p| p.exists())
+        .collect()
+}
+
+fn rotated_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("oondl.{}.log", n))
+}
+
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_owned(),
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..ROTATED_LOG_FILES).rev() {
+            let from = rotated_path(&self.dir, n);
+            if from.exists() {
+                fs::rename(from, rotated_path(&self.dir, n + 1))?;
+            }
+        }
+        fs::rename(self.dir.join(LOG_FILE_NAME), rotated_path(&self.dir, 1))?;
+        *self = Self::open(&self.dir)?;
+        Ok(())
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.size + line.len() as u64 > MAX_LOG_FILE_SIZE && self.size > 0 {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+struct Logger {
+    stderr: env_logger::Logger,
+    file: Option<Mutex<LogFile>>,
+}
+
+impl Logger {
+    /// Debug messages of this crate, like ffmpeg output and HTTP requests, always go to the
+    /// file, other crates are only logged from info level on.
+    fn file_enabled(&self, metadata: &Metadata) -> bool {
+        let max_level = if metadata.target().starts_with("oondl") {
+            Level::Debug
+        } else {
+            Level::Info
+        };
+        self.file.is_some() && metadata.level() <= max_level
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata) || self.file_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+        if self.file_enabled(record.metadata()) {
+            let line = format!(
+                "{} {:<5} {}: {}\n",
+                Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+            let mut file = self.file.as_ref().unwrap().lock().unwrap();
+            // there is nowhere left to report the error
+            let _ = file.write(&line);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().file.flush();
+        }
+    }
+}
+
+pub fn init() {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let file = log_dir().and_then(|dir| match LogFile::open(&dir) {
+        Ok(f) => Some(Mutex::new(f)),
+        Err(e) => {
+            eprintln!("could not open log file in {:?}: {}", dir, e);
+            None
+        }
+    });
+    let max_level = stderr.filter().max(if file.is_some() {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Off
+    });
+
+    log::set_boxed_logger(Box::new(Logger { stderr, file })).unwrap();
+    log::set_max_level(max_level);
+}
+
+/// Bundles the log files, version information and the settings into a zip archive.
+pub fn export_diagnostics(
+    dest: &Path,
+    settings: &Settings,
+    ffmpeg_version: Option<&str>,
+) -> anyhow::Result<()> {
+    log::logger().flush();
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(File::create(dest)?);
+
+    zip.start_file("version.txt", options)?;
+    writeln!(zip, "oondl {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(
+        zip,
+        "os: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    writeln!(zip, "ffmpeg: {}", ffmpeg_version.unwrap_or("-"))?;
+
+    zip.start_file("settings.json", options)?;
+    serde_json::to_writer_pretty(&mut zip, &settings.redacted())?;
+
+    if let Some(dir) = log_dir() {
+        for path in log_files(&dir) {
+            let name = path.file_name().unwrap().to_string_lossy();
+            zip.start_file(format!("logs/{}", name), options)?;
+            io::copy(&mut File::open(&path)?, &mut zip)?;
+        }
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let mut log_file = LogFile::open(dir).unwrap();
+        let line = format!("{}\n", "x".repeat(MAX_LOG_FILE_SIZE as usize / 2));
+
+        for _ in 0..7 {
+            log_file.write(&line).unwrap();
+        }
+        assert_eq!(
+            log_files(dir),
+            vec![
+                dir.join("oondl.2.log"),
+                dir.join("oondl.1.log"),
+                dir.join("oondl.log")
+            ]
+        );
+        for path in log_files(dir) {
+            assert!(fs::metadata(path).unwrap().len() <= MAX_LOG_FILE_SIZE);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 mod downloader;
 mod gui;
+mod logging;
 
 use eframe::NativeOptions;
 use egui::{Style, Visuals};
@@ -8,7 +9,7 @@ use single_instance::SingleInstance;
 
 fn main() -> eframe::Result<()> {
     const APP_NAME: &str = "oondl";
-    env_logger::init();
+    logging::init();
 
     let s = SingleInstance::new(APP_NAME).unwrap();
     if !s.is_single() {
@@ -33,8 +34,9 @@ fn main() -> eframe::Result<()> {
             };
             cc.egui_ctx.set_style(style);
 
-            let client = downloader::run(cc.egui_ctx.clone());
-            Box::new(OondlApp::new(cc, client))
+            let settings = gui::load_settings(cc);
+            let client = downloader::run(cc.egui_ctx.clone(), settings.clone());
+            Box::new(OondlApp::new(cc, client, settings))
         }),
     )
 }